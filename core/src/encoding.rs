@@ -0,0 +1,50 @@
+use encoding_rs::Encoding;
+use std::borrow::Cow;
+
+/// Encodings offered as fallback for files without a byte order mark.
+pub const FALLBACK_ENCODINGS: &[&str] = &[
+    "UTF-16LE",
+    "UTF-16BE",
+    "windows-1252",
+    "ISO-8859-2",
+    "ISO-8859-15",
+    "windows-1251",
+    "KOI8-R",
+    "Shift_JIS",
+    "EUC-JP",
+    "EUC-KR",
+    "GBK",
+    "gb18030",
+    "Big5",
+];
+
+/// Decodes file contents the same way they are decoded during a search.
+///
+/// A byte order mark takes precedence over the fallback encoding,
+/// and without either the content is treated as UTF-8.
+pub fn decode<'a>(bytes: &'a [u8], fallback: Option<&str>) -> Cow<'a, str> {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        return encoding.decode_without_bom_handling(&bytes[bom_length..]).0;
+    }
+
+    match fallback.and_then(|label| Encoding::for_label(label.as_bytes())) {
+        Some(encoding) => encoding.decode_without_bom_handling(bytes).0,
+        None => String::from_utf8_lossy(bytes),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::decode;
+
+    #[test]
+    fn test_decode() {
+        let utf16 = b"\xFF\xFEh\0i\0";
+        assert_eq!(decode(utf16, None), "hi");
+        assert_eq!(decode(utf16, Some("Shift_JIS")), "hi");
+
+        let latin1 = b"caf\xE9";
+        assert_eq!(decode(latin1, Some("windows-1252")), "café");
+        assert_eq!(decode(latin1, None), "caf\u{FFFD}");
+    }
+}
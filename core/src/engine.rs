@@ -1,76 +1,175 @@
 use crate::{
-    search::{self, SearchId, SearchParameters, SharedSearchId},
-    SearchMessage,
+    search::{self, SearchId, SearchParameters},
+    ErrorKind, SearchError, SearchMessage, SearchStats,
 };
 use flume::{Receiver, Sender};
+use gio::prelude::CancellableExt;
 use std::{
     path::PathBuf,
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
+    sync::atomic::{AtomicUsize, Ordering},
     thread,
 };
 
+/// Starts searches, each of which runs on its own threads.
+#[derive(Default)]
 pub struct SearchEngine {
-    pub(crate) sender: Sender<SearchMessage>,
-    pub(crate) receiver: Receiver<SearchMessage>,
-    pub(crate) current_search_id: SharedSearchId,
+    next_search_id: AtomicUsize,
 }
 
-impl Default for SearchEngine {
-    fn default() -> Self {
+impl SearchEngine {
+    pub fn search(&self, params: SearchParameters) -> SearchHandle {
+        let search = self.next_search_id.fetch_add(1, Ordering::Relaxed);
         let (sender, receiver) = flume::bounded(128);
+        let (completion_sender, completion) = flume::bounded(1);
+        let cancellable = gio::Cancellable::new();
 
-        SearchEngine {
+        let context = SearchContext {
+            search,
             sender,
+            completion: completion_sender,
+            cancellable: cancellable.clone(),
+        };
+        thread::spawn(move || search::run(context, params));
+
+        SearchHandle {
+            search,
             receiver,
-            current_search_id: Arc::new(AtomicUsize::new(0)),
+            completion,
+            cancellable,
         }
     }
 }
 
-impl SearchEngine {
+/// A running search, which is cancelled once the handle is dropped.
+///
+/// The messages of the search have to be received for it to make progress.
+pub struct SearchHandle {
+    search: SearchId,
+    receiver: Receiver<SearchMessage>,
+    completion: Receiver<SearchStats>,
+    cancellable: gio::Cancellable,
+}
+
+impl SearchHandle {
+    pub fn id(&self) -> SearchId {
+        self.search
+    }
+
     pub fn receiver(&self) -> Receiver<SearchMessage> {
         self.receiver.clone()
     }
 
-    pub fn search(&self, params: SearchParameters) {
-        self.current_search_id.fetch_add(1, Ordering::Release);
+    pub fn cancel(&self) {
+        self.cancellable.cancel();
+    }
 
-        let engine = self.clone();
-        thread::spawn(move || search::run(engine, params));
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellable.is_cancelled()
     }
 
-    pub fn cancel(&self) {
-        self.current_search_id.fetch_add(1, Ordering::Release);
+    /// Waits until the search has finished, which it also does after being cancelled.
+    pub async fn completed(&self) -> Option<SearchStats> {
+        self.completion.recv_async().await.ok()
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        self.cancel();
     }
+}
 
-    pub fn is_current(&self, message: &SearchMessage) -> bool {
-        let current = self.current_search_id.load(Ordering::Acquire);
+/// The side of a search which reports to its handle.
+#[derive(Clone)]
+pub(crate) struct SearchContext {
+    search: SearchId,
+    sender: Sender<SearchMessage>,
+    completion: Sender<SearchStats>,
+    cancellable: gio::Cancellable,
+}
 
-        message.search() == current
+impl SearchContext {
+    pub fn id(&self) -> SearchId {
+        self.search
     }
 
-    pub(crate) fn clone(&self) -> Self {
-        SearchEngine {
-            sender: self.sender.clone(),
-            receiver: self.receiver.clone(),
-            current_search_id: self.current_search_id.clone(),
-        }
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellable.is_cancelled()
+    }
+
+    /// Cancelled together with the search, for stopping work inside a single file.
+    pub fn cancellable(&self) -> &gio::Cancellable {
+        &self.cancellable
+    }
+
+    /// Sends a message, returning whether anyone is still listening.
+    pub fn send(&self, message: SearchMessage) -> bool {
+        self.sender.send(message).is_ok()
+    }
+
+    /// Sends a message unless the channel is full.
+    pub fn try_send(&self, message: SearchMessage) {
+        _ = self.sender.try_send(message);
+    }
+
+    pub fn send_error(&self, path: PathBuf, kind: ErrorKind, message: String) {
+        self.send(SearchMessage::Error(SearchError {
+            search: self.search,
+            kind,
+            path,
+            message,
+        }));
+    }
+
+    /// Reports why a search could not be started and completes it.
+    pub fn abort(&self, path: PathBuf, message: String) {
+        self.send_error(path, ErrorKind::Pattern, message);
+        self.complete(SearchStats::default());
     }
 
-    pub(crate) fn send_error(
-        &self,
-        search: SearchId,
-        path: PathBuf,
-        message: String,
-    ) -> Result<(), flume::SendError<SearchMessage>> {
-        self.sender
-            .send(SearchMessage::Error(crate::result::SearchError {
-                search,
-                path,
-                message,
-            }))
+    pub fn complete(&self, stats: SearchStats) {
+        self.send(SearchMessage::Completed {
+            search: self.search,
+            stats: stats.clone(),
+        });
+        _ = self.completion.send(stats);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SearchEngine;
+    use crate::{
+        testing::{search_parameters, TempDir},
+        SearchMessage,
+    };
+    use std::fs;
+
+    #[test]
+    fn test_concurrent_searches() {
+        let dir = TempDir::new("engine");
+        fs::write(dir.join("a.txt"), "apple\nbanana\n").unwrap();
+
+        let params = |pattern: &str| search_parameters(dir.path(), pattern);
+
+        let engine = SearchEngine::default();
+        let apple = engine.search(params("apple"));
+        let banana = engine.search(params("banana"));
+        assert_ne!(apple.id(), banana.id());
+
+        for (handle, expected) in [(apple, "apple"), (banana, "banana")] {
+            let mut contents = Vec::new();
+            for message in handle.receiver().iter() {
+                assert_eq!(message.search(), handle.id());
+                match message {
+                    SearchMessage::Result(result) => {
+                        contents.extend(result.entries.into_iter().map(|e| e.content))
+                    }
+                    SearchMessage::Completed { .. } => break,
+                    _ => {}
+                }
+            }
+            assert_eq!(contents, [expected]);
+        }
     }
 }
@@ -0,0 +1,157 @@
+use crate::extra::{compressed, CancellableReader};
+use std::{
+    env,
+    error::Error,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use zip::ZipArchive;
+
+/// The path of an entry inside an archive, like `bundle.zip!/src/main.rs`.
+pub fn entry_path(archive: &Path, inner_path: &Path) -> PathBuf {
+    PathBuf::from(format!("{}!/{}", archive.display(), inner_path.display()))
+}
+
+/// Calls `f` with the path and content of each file in a zip or tar archive,
+/// until it returns `false`.
+pub fn for_each_entry(
+    path: &Path,
+    mut f: impl FnMut(&Path, &mut dyn Read) -> bool,
+) -> Result<(), Box<dyn Error>> {
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+
+    if ext == "zip" {
+        let mut archive = ZipArchive::new(BufReader::new(File::open(path)?))?;
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i)?;
+            if !entry.is_file() {
+                continue;
+            }
+            // Entries with unsafe paths, like `../file`, are skipped.
+            let Some(inner_path) = entry.enclosed_name() else {
+                continue;
+            };
+            if !f(&inner_path, &mut entry) {
+                break;
+            }
+        }
+    } else {
+        let reader: Box<dyn Read> = match ext.as_str() {
+            "tar" => Box::new(BufReader::new(File::open(path)?)),
+            _ => compressed::open(path)?,
+        };
+
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let inner_path = entry.path()?.into_owned();
+            let inner_path = inner_path.strip_prefix(".").unwrap_or(&inner_path);
+            if !f(inner_path, &mut entry) {
+                break;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the content of a single file in an archive.
+pub fn read_entry(path: &Path, inner_path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut content = None;
+    for_each_entry(path, |entry_path, reader| {
+        if entry_path != inner_path {
+            return true;
+        }
+        let mut buffer = Vec::new();
+        content = Some(reader.read_to_end(&mut buffer).map(|_| buffer));
+        false
+    })?;
+
+    match content {
+        Some(content) => Ok(content?),
+        None => Err(format!("{} not found in archive", inner_path.display()).into()),
+    }
+}
+
+/// Calls `f` with the path of a temporary copy of an archive entry.
+///
+/// Preprocessors only work on files,
+/// so the entry is extracted to a temporary file first.
+pub fn with_temp_file<T>(
+    inner_path: &Path,
+    reader: &mut dyn Read,
+    cancellable: &gio::Cancellable,
+    f: impl FnOnce(&Path) -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = inner_path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = env::temp_dir().join(format!(
+        "clapgrep-{}-{}-{file_name}",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let result = File::create(&temp)
+        .and_then(|mut file| io::copy(&mut CancellableReader::new(reader, cancellable), &mut file))
+        .map_err(Into::into)
+        .and_then(|_| f(&temp));
+    _ = fs::remove_file(&temp);
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{entry_path, for_each_entry, read_entry};
+    use crate::testing::TempDir;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn test_tar_entries() {
+        let dir = TempDir::new("archive");
+        // Archives are recognized regardless of the case of their extension.
+        let path = dir.join("test.TAR");
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in [
+            ("./src/main.rs", "fn main() {}\n"),
+            ("README.md", "# Test\n"),
+        ] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, name, content.as_bytes())
+                .unwrap();
+        }
+        fs::write(&path, builder.into_inner().unwrap()).unwrap();
+
+        let mut inner_paths = Vec::new();
+        for_each_entry(&path, |inner_path, _| {
+            inner_paths.push(inner_path.to_path_buf());
+            true
+        })
+        .unwrap();
+        let content = read_entry(&path, Path::new("README.md"));
+
+        assert_eq!(
+            inner_paths,
+            [Path::new("src/main.rs"), Path::new("README.md")]
+        );
+        assert_eq!(content.unwrap(), b"# Test\n");
+        assert_eq!(
+            entry_path(Path::new("bundle.zip"), Path::new("src/main.rs")),
+            Path::new("bundle.zip!/src/main.rs")
+        );
+    }
+}
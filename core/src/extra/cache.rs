@@ -0,0 +1,237 @@
+use crate::{extra::ExtractedText, utils};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::{
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Text extracted from documents, stored with a file per document.
+///
+/// Documents are identified by their path, modification time and size, so
+/// changed documents are extracted again. Once the files take up more than
+/// the maximum size, the least recently used ones are removed.
+#[derive(Debug, Clone)]
+pub struct TextCache {
+    directory: PathBuf,
+    max_size: u64,
+    /// The size of all files, which is only counted once something is added.
+    size: Arc<Mutex<Option<u64>>>,
+}
+
+impl TextCache {
+    pub fn new(directory: PathBuf, max_size: u64) -> Self {
+        TextCache {
+            directory,
+            max_size,
+            size: Default::default(),
+        }
+    }
+
+    /// The text which an extractor extracted from a document,
+    /// if the document did not change since.
+    pub fn get(&self, extractor: &str, path: &Path, metadata: &Metadata) -> Option<ExtractedText> {
+        let file = self.file(extractor, path, metadata);
+        let text = read_entry(&file, path).ok()?;
+
+        // The modification time tells which files were used least recently.
+        if let Ok(file) = File::options().write(true).open(&file) {
+            _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(text)
+    }
+
+    pub fn insert(&self, extractor: &str, path: &Path, metadata: &Metadata, text: &ExtractedText) {
+        let file = self.file(extractor, path, metadata);
+        let written = fs::create_dir_all(&self.directory)
+            .and_then(|_| write_entry(&file, path, text))
+            .and_then(|_| fs::metadata(&file));
+
+        match written {
+            Ok(metadata) => self.grow(metadata.len()),
+            Err(err) => log::warn!("failed to cache text of {}: {err}", path.display()),
+        }
+    }
+
+    /// The size of all cached texts in bytes.
+    pub fn disk_usage(&self) -> u64 {
+        entries(&self.directory).iter().map(|entry| entry.1).sum()
+    }
+
+    pub fn clear(&self) -> io::Result<()> {
+        let mut size = self.size.lock().unwrap();
+        match fs::remove_dir_all(&self.directory) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => {}
+        }
+        *size = Some(0);
+        Ok(())
+    }
+
+    fn file(&self, extractor: &str, path: &Path, metadata: &Metadata) -> PathBuf {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        let key = format!(
+            "{extractor}\0{}\0{}\0{}",
+            path.display(),
+            modified.as_nanos(),
+            metadata.len()
+        );
+        self.directory
+            .join(format!("{:016x}", utils::stable_hash(key.as_bytes())))
+    }
+
+    /// Counts an added file and removes the least recently used files if there are too many.
+    fn grow(&self, added: u64) {
+        let mut size = self.size.lock().unwrap();
+        let total = match *size {
+            Some(size) => size + added,
+            None => self.disk_usage(),
+        };
+
+        if total <= self.max_size {
+            *size = Some(total);
+            return;
+        }
+
+        // Evict more than necessary, so that this does not happen for every added file.
+        let mut entries = entries(&self.directory);
+        entries.sort_by_key(|(modified, _, _)| *modified);
+        let mut total = entries.iter().map(|entry| entry.1).sum::<u64>();
+        for (_, len, file) in entries {
+            if total <= self.max_size / 4 * 3 {
+                break;
+            }
+            if fs::remove_file(file).is_ok() {
+                total -= len;
+            }
+        }
+        *size = Some(total);
+    }
+}
+
+/// The modification time, size and path of the files in a directory.
+fn entries(directory: &Path) -> Vec<(SystemTime, u64, PathBuf)> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+            Some((modified, metadata.len(), entry.path()))
+        })
+        .collect()
+}
+
+fn write_entry(file: &Path, path: &Path, text: &ExtractedText) -> io::Result<()> {
+    let (kind, pages) = match text {
+        ExtractedText::Text(text) => (0, std::slice::from_ref(text)),
+        ExtractedText::Pages(pages) => (1, pages.as_slice()),
+    };
+
+    // Written to another file first, so that readers never see a partial entry.
+    let temp = utils::temp_path(file);
+    let written = File::create(&temp).and_then(|temp| {
+        let mut writer = GzEncoder::new(BufWriter::new(temp), Compression::fast());
+        write_bytes(&mut writer, path.to_string_lossy().as_bytes())?;
+        writer.write_all(&[kind])?;
+        writer.write_all(&(pages.len() as u64).to_le_bytes())?;
+        for page in pages {
+            write_bytes(&mut writer, page.as_bytes())?;
+        }
+        writer.finish()?.flush()
+    });
+
+    written
+        .and_then(|_| fs::rename(&temp, file))
+        .inspect_err(|_| {
+            _ = fs::remove_file(&temp);
+        })
+}
+
+fn read_entry(file: &Path, path: &Path) -> io::Result<ExtractedText> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+    let mut reader = GzDecoder::new(BufReader::new(File::open(file)?));
+
+    // Different documents might end up with the same file.
+    if read_bytes(&mut reader)? != path.to_string_lossy().as_bytes() {
+        return Err(invalid("the entry belongs to another document"));
+    }
+
+    let mut kind = [0];
+    reader.read_exact(&mut kind)?;
+    let mut count = [0; 8];
+    reader.read_exact(&mut count)?;
+
+    let mut pages = Vec::new();
+    for _ in 0..u64::from_le_bytes(count) {
+        let page = String::from_utf8(read_bytes(&mut reader)?)
+            .map_err(|_| invalid("the text is not valid UTF-8"))?;
+        pages.push(page);
+    }
+
+    match kind {
+        [0] if pages.len() == 1 => Ok(ExtractedText::Text(pages.remove(0))),
+        [1] => Ok(ExtractedText::Pages(pages)),
+        _ => Err(invalid("unknown kind of text")),
+    }
+}
+
+fn write_bytes(writer: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    writer.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    writer.write_all(bytes)
+}
+
+fn read_bytes(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 8];
+    reader.read_exact(&mut len)?;
+    let mut bytes = Vec::new();
+    reader
+        .take(u64::from_le_bytes(len))
+        .read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::TextCache;
+    use crate::{extra::ExtractedText, testing::TempDir};
+    use std::fs;
+
+    #[test]
+    fn test_text_cache() {
+        let dir = TempDir::new("cache");
+        let document = dir.join("report.pdf");
+        fs::write(&document, "not really a pdf").unwrap();
+        let metadata = fs::metadata(&document).unwrap();
+
+        let cache = TextCache::new(dir.join("cache"), 1_000_000);
+        let text = ExtractedText::Pages(vec!["first page".into(), "second page".into()]);
+        assert_eq!(cache.get("PDF", &document, &metadata), None);
+        cache.insert("PDF", &document, &metadata, &text);
+        assert_eq!(cache.get("PDF", &document, &metadata), Some(text));
+        assert_eq!(cache.get("Office", &document, &metadata), None);
+
+        // Changed documents are not found anymore.
+        fs::write(&document, "changed").unwrap();
+        let changed = fs::metadata(&document).unwrap();
+        assert_eq!(cache.get("PDF", &document, &changed), None);
+
+        // Filling up the cache evicts the least recently used texts.
+        let small = TextCache::new(dir.join("small"), 1);
+        small.insert("PDF", &document, &changed, &ExtractedText::Text("a".into()));
+        assert_eq!(small.get("PDF", &document, &changed), None);
+
+        cache.clear().unwrap();
+        assert_eq!(cache.disk_usage(), 0);
+    }
+}
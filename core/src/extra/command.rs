@@ -0,0 +1,186 @@
+use crate::{
+    extra::{ExtractedText, Extractor},
+    file_types::FileType,
+    ErrorKind,
+};
+use gio::prelude::CancellableExt;
+use std::{
+    error::Error,
+    io::{self, Read},
+    mem,
+    path::Path,
+    process::{Command, Stdio},
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Runs an external command with the path of a file appended to its arguments,
+/// which prints the text of the file, like the `--pre` option of ripgrep.
+pub struct CommandExtractor {
+    file_type: FileType,
+    argv: Vec<String>,
+    timeout: Duration,
+}
+
+impl CommandExtractor {
+    pub fn new(file_type: FileType, argv: Vec<String>, timeout: Duration) -> Self {
+        CommandExtractor {
+            file_type,
+            argv,
+            timeout,
+        }
+    }
+}
+
+impl Extractor for CommandExtractor {
+    fn file_type(&self) -> FileType {
+        self.file_type.clone()
+    }
+
+    fn extract(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+    ) -> Result<ExtractedText, Box<dyn Error>> {
+        let Some((program, args)) = self.argv.split_first() else {
+            return Err("the preprocessor command is empty".into());
+        };
+
+        let mut child = Command::new(program)
+            .args(args)
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| format!("failed to run {program}: {err}"))?;
+
+        // Both pipes are drained while waiting, so that the command never blocks on them.
+        // Processes started by the command may keep them open after it exited,
+        // so the timeout applies until everything was read.
+        let stdout_reader = read_to_end(child.stdout.take());
+        let stderr_reader = read_to_end(child.stderr.take());
+        let (mut status, mut stdout, mut stderr) = (None, None, None);
+
+        let started = Instant::now();
+        let (status, stdout, stderr) = loop {
+            if status.is_none() {
+                status = child.try_wait()?;
+            }
+            if stdout.is_none() {
+                stdout = stdout_reader.try_recv().ok().transpose()?;
+            }
+            if stderr.is_none() {
+                stderr = stderr_reader.try_recv().ok().transpose()?;
+            }
+
+            if let (Some(status), Some(stdout), Some(stderr)) = (status, &mut stdout, &mut stderr) {
+                break (status, mem::take(stdout), mem::take(stderr));
+            }
+
+            if cancellable.is_cancelled() || started.elapsed() > self.timeout {
+                if status.is_none() {
+                    _ = child.kill();
+                    _ = child.wait();
+                }
+                cancellable.set_error_if_cancelled()?;
+                return Err(format!(
+                    "{program} timed out after {} seconds",
+                    self.timeout.as_secs_f32()
+                )
+                .into());
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        };
+
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            return Err(format!("{program} failed with {status}: {}", stderr.trim()).into());
+        }
+
+        Ok(ExtractedText::Text(
+            crate::decode(&stdout, None).into_owned(),
+        ))
+    }
+
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::Preprocessor
+    }
+}
+
+/// Reads the pipe on another thread, which sends the content once the pipe is closed.
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> Receiver<io::Result<Vec<u8>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut content = Vec::new();
+        let result = match pipe {
+            Some(mut pipe) => pipe.read_to_end(&mut content).map(|_| content),
+            None => Ok(content),
+        };
+        _ = sender.send(result);
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod test {
+    use super::CommandExtractor;
+    use crate::{
+        extra::{ExtractedText, Extractor},
+        file_types::FileType,
+        testing::TempDir,
+    };
+    use std::{
+        fs,
+        path::Path,
+        time::{Duration, Instant},
+    };
+
+    fn extractor(argv: &[&str]) -> CommandExtractor {
+        CommandExtractor::new(
+            FileType::new("Test", ["*.test"]),
+            argv.iter().map(|arg| arg.to_string()).collect(),
+            Duration::from_secs(5),
+        )
+    }
+
+    #[test]
+    fn test_command_extractor() {
+        let dir = TempDir::new("command");
+        let path = dir.join("file.test");
+        fs::write(&path, "some text\n").unwrap();
+        let cancellable = gio::Cancellable::new();
+
+        let text = extractor(&["cat"]).extract(&path, &cancellable);
+        let failed = extractor(&["cat", "--no-such-option"]).extract(&path, &cancellable);
+        let missing = extractor(&["cat"]).extract(Path::new("/no/such/file"), &cancellable);
+
+        assert_eq!(
+            text.unwrap(),
+            ExtractedText::Text("some text\n".to_string())
+        );
+        assert!(failed.unwrap_err().to_string().contains("cat failed"));
+        assert!(missing.unwrap_err().to_string().contains("/no/such/file"));
+    }
+
+    #[test]
+    fn test_command_timeout() {
+        let dir = TempDir::new("command-timeout");
+        let path = dir.join("file.test");
+        fs::write(&path, "some text\n").unwrap();
+
+        // The background process keeps the output open after the command exited.
+        let extractor = CommandExtractor::new(
+            FileType::new("Test", ["*.test"]),
+            vec!["sh".into(), "-c".into(), "sleep 3 & cat \"$0\"".into()],
+            Duration::from_millis(200),
+        );
+
+        let started = Instant::now();
+        let result = extractor.extract(&path, &gio::Cancellable::new());
+        assert!(result.unwrap_err().to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{extra::CancellableReader, matcher::SearchMatcher, search::SearchSink};
+use grep::searcher::Searcher;
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+pub fn process(
+    searcher: &mut Searcher,
+    matcher: &SearchMatcher,
+    path: &Path,
+    sink: &mut SearchSink,
+    cancellable: &gio::Cancellable,
+) -> Result<(), Box<dyn Error>> {
+    let reader = CancellableReader::new(open(path)?, cancellable);
+    searcher.search_reader(matcher, reader, sink)
+}
+
+/// Opens a compressed file for reading its decompressed content.
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_ascii_lowercase();
+    let file = BufReader::new(File::open(path)?);
+
+    let reader: Box<dyn Read> = match ext.as_str() {
+        "gz" | "tgz" => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+        "xz" => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(file)),
+        "bz2" => Box::new(bzip2::bufread::MultiBzDecoder::new(file)),
+        "zst" => Box::new(zstd::stream::read::Decoder::with_buffer(file)?),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "unknown compression format",
+            ))
+        }
+    };
+
+    Ok(reader)
+}
+
+/// Reads the whole decompressed content of a file.
+pub fn read(path: &Path) -> io::Result<Vec<u8>> {
+    let mut content = Vec::new();
+    open(path)?.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// The path of a compressed file without its compression extension,
+/// for example `syslog.2` for `syslog.2.gz`.
+pub fn inner_path(path: &Path) -> &Path {
+    let ext = path.extension().and_then(|ext| ext.to_str());
+    match ext.map(str::to_ascii_lowercase).as_deref() {
+        Some("gz" | "xz" | "bz2" | "zst") => Path::new(path.file_stem().unwrap_or_default()),
+        _ => path,
+    }
+}
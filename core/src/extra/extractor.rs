@@ -0,0 +1,242 @@
+use crate::{
+    file_types::{FileType, FileTypeMatcher, FileTypes},
+    ErrorKind,
+};
+use std::{
+    error::Error,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+
+/// Text extracted from a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractedText {
+    /// Text without pages, whose results are located by line.
+    Text(String),
+    /// Pages or slides, whose results are located by page and line.
+    Pages(Vec<String>),
+}
+
+/// Receives the text of a document page by page, with the number
+/// of each page starting at 1, or once without a number for text without pages.
+pub type PageFn<'a> = dyn FnMut(Option<u64>, &str) -> Result<(), Box<dyn Error>> + 'a;
+
+impl ExtractedText {
+    pub fn for_each_page(&self, f: &mut PageFn) -> Result<(), Box<dyn Error>> {
+        match self {
+            ExtractedText::Text(text) => f(None, text),
+            ExtractedText::Pages(pages) => pages
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, page)| f(Some(i as u64 + 1), page)),
+        }
+    }
+}
+
+/// Turns files in a format other than plain text into searchable text.
+///
+/// Implementations are registered in an [`ExtractorRegistry`],
+/// which is passed to the search with its parameters.
+pub trait Extractor: Send + Sync {
+    /// The files handled by this extractor, like `*.pdf` for `PDF`.
+    fn file_type(&self) -> FileType;
+
+    /// MIME types of the handled files, like `application/pdf`.
+    fn mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn extract(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+    ) -> Result<ExtractedText, Box<dyn Error>>;
+
+    /// Extracts the text like `extract`, but hands each page to `f` as soon as it
+    /// is extracted, so large documents are neither kept in memory nor waited for.
+    fn extract_pages(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+        f: &mut PageFn,
+    ) -> Result<(), Box<dyn Error>> {
+        self.extract(path, cancellable)?.for_each_page(f)
+    }
+
+    /// Whether the extracted text can be shown as a preview of the file.
+    fn supports_preview(&self) -> bool {
+        false
+    }
+
+    /// Whether the extracted text only depends on the content of the file,
+    /// so that it can be cached and indexed until the file changes.
+    fn is_cacheable(&self) -> bool {
+        false
+    }
+
+    /// The kind of extraction errors which are not recognized otherwise.
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::Io
+    }
+}
+
+/// Extractors by the files they handle, where earlier ones take precedence.
+#[derive(Clone, Default)]
+pub struct ExtractorRegistry {
+    extractors: Vec<(FileTypeMatcher, Arc<dyn Extractor>)>,
+}
+
+impl fmt::Debug for ExtractorRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.extractors.iter().map(|(_, e)| e.file_type().name))
+            .finish()
+    }
+}
+
+impl ExtractorRegistry {
+    /// The registry with the built-in PDF and office extractors.
+    pub fn builtin() -> Self {
+        let mut registry = ExtractorRegistry::default();
+        registry.add_builtin(true, true);
+        registry
+    }
+
+    /// Adds an extractor, which fails if its globs are invalid.
+    pub fn register(&mut self, extractor: impl Extractor + 'static) -> Result<(), ignore::Error> {
+        let types = FileTypeMatcher::new(&[extractor.file_type()])?;
+        self.extractors.push((types, Arc::new(extractor)));
+        Ok(())
+    }
+
+    /// Adds the selected built-in extractors after the registered ones.
+    pub(crate) fn add_builtin(&mut self, pdf: bool, office: bool) {
+        if pdf {
+            self.register(super::pdf::PdfExtractor)
+                .expect("built-in types are valid");
+        }
+        if office {
+            self.register(super::office::OfficeExtractor)
+                .expect("built-in types are valid");
+        }
+    }
+
+    /// The extractor for a file by its name, ignoring the case of its extension.
+    pub fn find(&self, path: &Path) -> Option<&Arc<dyn Extractor>> {
+        let lowercase = lowercase_name(path);
+        self.extractors
+            .iter()
+            .find(|(types, _)| {
+                types.is_match(path) || lowercase.as_ref().is_some_and(|p| types.is_match(p))
+            })
+            .map(|(_, extractor)| extractor)
+    }
+
+    /// The extractor for a file by its name, or otherwise by its content,
+    /// so that documents without their usual extension are found too.
+    ///
+    /// Only files without an extension, or with one of no built-in
+    /// file type, are read, since others are known not to be documents.
+    pub fn detect(&self, path: &Path) -> Option<&Arc<dyn Extractor>> {
+        self.find(path).or_else(|| {
+            // Only read the file if an extractor could handle it.
+            let handles_mime_types = self
+                .extractors
+                .iter()
+                .any(|(_, extractor)| !extractor.mime_types().is_empty());
+            (handles_mime_types && has_unknown_extension(path))
+                .then(|| super::sniff::mime_type(path))
+                .flatten()
+                .and_then(|mime_type| self.find_by_mime_type(mime_type))
+        })
+    }
+
+    /// The extractor for files of a MIME type, if there is one.
+    pub fn find_by_mime_type(&self, mime_type: &str) -> Option<&Arc<dyn Extractor>> {
+        self.extractors
+            .iter()
+            .map(|(_, extractor)| extractor)
+            .find(|extractor| extractor.mime_types().iter().any(|m| m == mime_type))
+    }
+}
+
+/// The path with its file name in lowercase.
+fn lowercase_name(path: &Path) -> Option<PathBuf> {
+    path.file_name()
+        .map(|name| path.with_file_name(name.to_string_lossy().to_lowercase()))
+}
+
+fn has_unknown_extension(path: &Path) -> bool {
+    static BUILTIN: OnceLock<FileTypeMatcher> = OnceLock::new();
+    let builtin = BUILTIN.get_or_init(|| {
+        let types = FileTypes::builtin().iter().cloned().collect::<Vec<_>>();
+        FileTypeMatcher::new(&types).expect("built-in types are valid")
+    });
+
+    path.extension().is_none() || lowercase_name(path).is_none_or(|p| !builtin.is_match(&p))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ExtractedText, Extractor, ExtractorRegistry};
+    use crate::{file_types::FileType, testing::TempDir};
+    use std::{error::Error, fs, path::Path};
+
+    struct NoteExtractor;
+
+    impl Extractor for NoteExtractor {
+        fn file_type(&self) -> FileType {
+            FileType::new("Notes", ["*.note", "*.pdf"])
+        }
+
+        fn extract(
+            &self,
+            path: &Path,
+            _cancellable: &gio::Cancellable,
+        ) -> Result<ExtractedText, Box<dyn Error>> {
+            let content = fs::read_to_string(path)?;
+            Ok(ExtractedText::Pages(
+                content.split('\x0c').map(String::from).collect(),
+            ))
+        }
+    }
+
+    #[test]
+    fn test_registered_extractor() {
+        let mut registry = ExtractorRegistry::default();
+        registry.register(NoteExtractor).unwrap();
+        registry.add_builtin(true, true);
+
+        let name = |path: &str| {
+            registry
+                .find(Path::new(path))
+                .map(|extractor| extractor.file_type().name)
+        };
+        assert_eq!(name("a.note").as_deref(), Some("Notes"));
+        assert_eq!(name("a.pdf").as_deref(), Some("Notes"));
+        assert_eq!(name("a.docx").as_deref(), Some("Office"));
+        assert_eq!(name("A.DOCX").as_deref(), Some("Office"));
+        assert_eq!(name("a.txt"), None);
+        assert!(registry.find_by_mime_type("application/pdf").is_some());
+    }
+
+    #[test]
+    fn test_detect_by_content() {
+        let dir = TempDir::new("detect");
+        for name in ["report", "report.bak", "report.txt"] {
+            fs::write(dir.join(name), "%PDF-1.7\n").unwrap();
+        }
+        let registry = ExtractorRegistry::builtin();
+        let name = |path: &str| {
+            registry
+                .detect(&dir.join(path))
+                .map(|extractor| extractor.file_type().name)
+        };
+
+        assert_eq!(name("report").as_deref(), Some("PDF"));
+        assert_eq!(name("report.bak").as_deref(), Some("PDF"));
+        // Files of other known types are not read.
+        assert_eq!(name("report.txt"), None);
+    }
+}
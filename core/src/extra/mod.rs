@@ -1,9 +1,55 @@
-use crate::search::SearchSink;
-use grep::{regex::RegexMatcher, searcher::Searcher};
-use std::{error::Error, path::Path};
+use crate::{matcher::SearchMatcher, search::SearchSink};
+use gio::prelude::CancellableExt;
+use grep::searcher::Searcher;
+use std::{
+    error::Error,
+    io::{self, Read},
+};
 
+pub mod archive;
+pub mod cache;
+pub mod command;
+pub mod compressed;
 pub mod office;
 pub mod pdf;
+pub mod sniff;
 
-pub type ExtraFn =
-    fn(&mut Searcher, &RegexMatcher, &Path, &mut SearchSink) -> Result<(), Box<dyn Error>>;
+mod extractor;
+pub use extractor::{ExtractedText, Extractor, ExtractorRegistry, PageFn};
+
+/// Searches a page of the text extracted from a document.
+pub(crate) fn search_page(
+    searcher: &mut Searcher,
+    matcher: &SearchMatcher,
+    sink: &mut SearchSink,
+    page: Option<u64>,
+    text: &str,
+) -> Result<(), Box<dyn Error>> {
+    sink.page = page;
+    searcher.search_slice(matcher, text.as_bytes(), sink)
+}
+
+/// A reader which fails once the search is cancelled,
+/// so that streams are not read to their end in vain.
+pub struct CancellableReader<R> {
+    inner: R,
+    cancellable: gio::Cancellable,
+}
+
+impl<R: Read> CancellableReader<R> {
+    pub fn new(inner: R, cancellable: &gio::Cancellable) -> Self {
+        CancellableReader {
+            inner,
+            cancellable: cancellable.clone(),
+        }
+    }
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.cancellable.is_cancelled() {
+            return Err(io::Error::other("search was cancelled"));
+        }
+        self.inner.read(buf)
+    }
+}
@@ -1,71 +1,101 @@
 #![allow(clippy::single_match)]
 
-use crate::search::SearchSink;
+use crate::{
+    extra::{sniff, ExtractedText, Extractor},
+    file_types::{FileType, FileTypes, OFFICE},
+    ErrorKind,
+};
 use anyhow::{anyhow, Context};
-use grep::{regex::RegexMatcher, searcher::Searcher};
-use std::{error::Error, fs::File, io::Read, mem, path::Path};
+use gio::prelude::CancellableExt;
+use std::{error::Error, fs::File, io::Read, mem, path::Path, sync::OnceLock};
 use xml::{events::Event, Reader};
 use zip::ZipArchive;
 
-pub static EXTENSIONS: &[&str] = &["docx", "pptx", "xlsx", "odt", "odp", "ods"];
-
 pub type Document = String;
 pub type Slides = Vec<String>;
 
-pub fn process(
-    searcher: &mut Searcher,
-    matcher: &RegexMatcher,
-    path: &Path,
-    sink: &mut SearchSink,
-) -> Result<(), Box<dyn Error>> {
-    let ext = path.extension().unwrap_or_default().to_string_lossy();
-
-    match ext.as_ref() {
-        "pptx" | "odp" => process_presentation(searcher, matcher, path, sink),
-        "docx" | "xlsx" | "odt" | "ods" => process_document(searcher, matcher, path, sink),
-        _ => Err("unknown extension".into()),
+/// The extensions of the supported formats with their MIME types.
+const FORMATS: [(&str, &str); 6] = [
+    ("docx", sniff::DOCX),
+    ("xlsx", sniff::XLSX),
+    ("pptx", sniff::PPTX),
+    ("odt", sniff::ODT),
+    ("ods", sniff::ODS),
+    ("odp", sniff::ODP),
+];
+
+pub struct OfficeExtractor;
+
+impl Extractor for OfficeExtractor {
+    fn file_type(&self) -> FileType {
+        static FILE_TYPE: OnceLock<FileType> = OnceLock::new();
+        FILE_TYPE
+            .get_or_init(|| {
+                let types = FileTypes::builtin();
+                let globs = OFFICE
+                    .iter()
+                    .filter_map(|name| types.get(name))
+                    .flat_map(|file_type| file_type.globs.clone());
+                FileType::new("Office", globs)
+            })
+            .clone()
     }
-}
 
-fn process_document(
-    searcher: &mut Searcher,
-    matcher: &RegexMatcher,
-    path: &Path,
-    sink: &mut SearchSink,
-) -> Result<(), Box<dyn Error>> {
-    let ext = path.extension().unwrap_or_default().to_string_lossy();
-
-    let string: Document = match ext.as_ref() {
-        "docx" => open_docx(path)?,
-        "xlsx" => open_xlsx(path)?,
-        "odt" => open_odt(path)?,
-        "ods" => open_ods(path)?,
-        _ => unreachable!(),
-    };
-
-    searcher.search_slice(matcher, string.as_bytes(), sink)
-}
+    fn mime_types(&self) -> Vec<String> {
+        FORMATS
+            .iter()
+            .map(|(_, mime_type)| mime_type.to_string())
+            .collect()
+    }
+
+    fn extract(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+    ) -> Result<ExtractedText, Box<dyn Error>> {
+        let text = match format(path) {
+            "docx" => ExtractedText::Text(open_docx(path, cancellable)?),
+            "xlsx" => ExtractedText::Text(open_xlsx(path, cancellable)?),
+            "odt" => ExtractedText::Text(open_odt(path, cancellable)?),
+            "ods" => ExtractedText::Text(open_ods(path, cancellable)?),
+            "pptx" => ExtractedText::Pages(open_pptx(path, cancellable)?),
+            "odp" => ExtractedText::Pages(open_odp(path, cancellable)?),
+            _ => return Err("unknown document format".into()),
+        };
+
+        Ok(text)
+    }
 
-fn process_presentation(
-    searcher: &mut Searcher,
-    matcher: &RegexMatcher,
-    path: &Path,
-    sink: &mut SearchSink,
-) -> Result<(), Box<dyn Error>> {
-    let ext = path.extension().unwrap_or_default().to_string_lossy();
-
-    let slides: Slides = match ext.as_ref() {
-        "pptx" => open_pptx(path)?,
-        "odp" => open_odp(path)?,
-        _ => unreachable!(),
-    };
-
-    for (i, slide) in slides.iter().enumerate() {
-        sink.page = Some(i as u64 + 1);
-        searcher.search_slice(matcher, slide.as_bytes(), &mut *sink)?
+    fn supports_preview(&self) -> bool {
+        true
     }
 
-    Ok(())
+    fn is_cacheable(&self) -> bool {
+        true
+    }
+
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::InvalidOfficeArchive
+    }
+}
+
+/// The extension of the format of a document, detected by its content
+/// if the actual extension is not one of a supported format.
+fn format(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_lowercase();
+
+    FORMATS
+        .iter()
+        .find(|(known, _)| *known == ext)
+        .or_else(|| {
+            let mime_type = sniff::mime_type(path)?;
+            FORMATS.iter().find(|(_, known)| *known == mime_type)
+        })
+        .map_or("", |(format, _)| format)
 }
 
 fn get_pptx_slide_index(file_name: &str) -> anyhow::Result<u64> {
@@ -78,13 +108,17 @@ fn get_pptx_slide_index(file_name: &str) -> anyhow::Result<u64> {
         .context("failed to parse slide index")
 }
 
-pub fn open_pptx<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
+pub fn open_pptx<P: AsRef<Path>>(
+    path: P,
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Slides> {
     let file = File::open(path.as_ref())?;
     let mut archive = ZipArchive::new(file)?;
 
     let mut xml_data = Vec::new();
 
     for i in 0..archive.len() {
+        cancellable.set_error_if_cancelled()?;
         let mut c_file = archive.by_index(i).unwrap();
         if c_file.name().starts_with("ppt/slides/slide") {
             let slide_idx = get_pptx_slide_index(c_file.name())?;
@@ -105,6 +139,7 @@ pub fn open_pptx<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
         let mut xml_reader = Reader::from_str(slide_xml_data);
         let mut text = String::new();
         loop {
+            cancellable.set_error_if_cancelled()?;
             match xml_reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => match e.name().into_inner() {
                     b"a:p" => {
@@ -139,13 +174,14 @@ pub fn open_pptx<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
     Ok(slides)
 }
 
-pub fn open_odp<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
+pub fn open_odp<P: AsRef<Path>>(path: P, cancellable: &gio::Cancellable) -> anyhow::Result<Slides> {
     let file = File::open(path.as_ref())?;
     let mut archive = ZipArchive::new(file)?;
 
     let mut xml_data = String::new();
 
     for i in 0..archive.len() {
+        cancellable.set_error_if_cancelled()?;
         let mut c_file = archive.by_index(i).unwrap();
         if c_file.name() == "content.xml" {
             c_file.read_to_string(&mut xml_data)?;
@@ -162,6 +198,7 @@ pub fn open_odp<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
         let mut to_read = false;
         let mut text = String::new();
         loop {
+            cancellable.set_error_if_cancelled()?;
             match xml_reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => match e.name().into_inner() {
                     b"text:p" => {
@@ -201,13 +238,17 @@ pub fn open_odp<P: AsRef<Path>>(path: P) -> anyhow::Result<Slides> {
     Ok(slides)
 }
 
-pub fn open_docx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
+pub fn open_docx<P: AsRef<Path>>(
+    path: P,
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Document> {
     let file = File::open(path.as_ref())?;
     let mut archive = ZipArchive::new(file)?;
 
     let mut xml_data = String::new();
 
     for i in 0..archive.len() {
+        cancellable.set_error_if_cancelled()?;
         let mut c_file = archive.by_index(i).unwrap();
         if c_file.name() == "word/document.xml" {
             c_file.read_to_string(&mut xml_data)?;
@@ -223,6 +264,7 @@ pub fn open_docx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
     if !xml_data.is_empty() {
         let mut to_read = false;
         loop {
+            cancellable.set_error_if_cancelled()?;
             match xml_reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => match e.name().into_inner() {
                     b"w:p" => {
@@ -254,7 +296,10 @@ pub fn open_docx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
     Ok(txt.join(""))
 }
 
-pub fn open_xlsx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
+pub fn open_xlsx<P: AsRef<Path>>(
+    path: P,
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Document> {
     let file = File::open(path.as_ref())?;
     let mut archive = ZipArchive::new(file)?;
 
@@ -262,6 +307,7 @@ pub fn open_xlsx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
     //        let xml_data_list = Vec::new();
 
     for i in 0..archive.len() {
+        cancellable.set_error_if_cancelled()?;
         let mut c_file = archive.by_index(i).unwrap();
         if c_file.name() == "xl/sharedStrings.xml"
             || c_file.name().starts_with("xl/charts/")
@@ -280,6 +326,7 @@ pub fn open_xlsx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
         let mut to_read = false;
         let mut xml_reader = Reader::from_str(xml_data.as_ref());
         loop {
+            cancellable.set_error_if_cancelled()?;
             match xml_reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => match e.name().into_inner() {
                     b"t" => {
@@ -315,13 +362,19 @@ pub fn open_xlsx<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
     Ok(txt.join(""))
 }
 
-fn open_od<P: AsRef<Path>>(path: P, content_name: &str, tags: &[&str]) -> anyhow::Result<Document> {
+fn open_od<P: AsRef<Path>>(
+    path: P,
+    content_name: &str,
+    tags: &[&str],
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Document> {
     let file = File::open(path.as_ref())?;
     let mut archive = ZipArchive::new(file)?;
 
     let mut xml_data = String::new();
 
     for i in 0..archive.len() {
+        cancellable.set_error_if_cancelled()?;
         let mut c_file = archive.by_index(i).unwrap();
         if c_file.name() == content_name {
             c_file.read_to_string(&mut xml_data)?;
@@ -337,6 +390,7 @@ fn open_od<P: AsRef<Path>>(path: P, content_name: &str, tags: &[&str]) -> anyhow
     if !xml_data.is_empty() {
         let mut to_read = false;
         loop {
+            cancellable.set_error_if_cancelled()?;
             match xml_reader.read_event_into(&mut buf) {
                 Ok(Event::Start(ref e)) => {
                     for tag in tags {
@@ -371,10 +425,16 @@ fn open_od<P: AsRef<Path>>(path: P, content_name: &str, tags: &[&str]) -> anyhow
     Ok(txt.join(""))
 }
 
-pub fn open_odt<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
-    open_od(path.as_ref(), "content.xml", &["text:p"])
+pub fn open_odt<P: AsRef<Path>>(
+    path: P,
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Document> {
+    open_od(path.as_ref(), "content.xml", &["text:p"], cancellable)
 }
 
-pub fn open_ods<P: AsRef<Path>>(path: P) -> anyhow::Result<Document> {
-    open_od(path.as_ref(), "content.xml", &["text:p"])
+pub fn open_ods<P: AsRef<Path>>(
+    path: P,
+    cancellable: &gio::Cancellable,
+) -> anyhow::Result<Document> {
+    open_od(path.as_ref(), "content.xml", &["text:p"], cancellable)
 }
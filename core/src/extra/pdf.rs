@@ -1,23 +1,65 @@
-use crate::search::SearchSink;
-use grep::{regex::RegexMatcher, searcher::Searcher};
+use crate::{
+    extra::{sniff, ExtractedText, Extractor, PageFn},
+    file_types::{FileType, FileTypes, PDF},
+    ErrorKind,
+};
+use gio::prelude::CancellableExt;
 use poppler::Document;
-use std::{error::Error, path::Path};
+use std::{error::Error, path::Path, sync::OnceLock};
 
-pub static EXTENSIONS: &[&str] = &["pdf"];
+pub struct PdfExtractor;
 
-pub fn process(
-    searcher: &mut Searcher,
-    matcher: &RegexMatcher,
-    path: &Path,
-    sink: &mut SearchSink,
-) -> Result<(), Box<dyn Error>> {
-    let doc = Document::from_gfile(&gio::File::for_path(path), None, gio::Cancellable::NONE)?;
-    for i in 0..doc.n_pages() {
-        let page = doc.page(i).expect("out of range");
-        if let Some(text) = page.text() {
-            sink.page = Some(i as u64 + 1);
-            searcher.search_slice(matcher, text.as_bytes(), &mut *sink)?
+impl Extractor for PdfExtractor {
+    fn file_type(&self) -> FileType {
+        static FILE_TYPE: OnceLock<FileType> = OnceLock::new();
+        FILE_TYPE
+            .get_or_init(|| {
+                FileTypes::builtin()
+                    .get(PDF)
+                    .cloned()
+                    .expect("PDF is a built-in type")
+            })
+            .clone()
+    }
+
+    fn mime_types(&self) -> Vec<String> {
+        vec![sniff::PDF.to_string()]
+    }
+
+    fn extract(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+    ) -> Result<ExtractedText, Box<dyn Error>> {
+        let mut pages = Vec::new();
+        self.extract_pages(path, cancellable, &mut |_, page| {
+            pages.push(page.to_string());
+            Ok(())
+        })?;
+        Ok(ExtractedText::Pages(pages))
+    }
+
+    fn extract_pages(
+        &self,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+        f: &mut PageFn,
+    ) -> Result<(), Box<dyn Error>> {
+        let doc = Document::from_gfile(&gio::File::for_path(path), None, Some(cancellable))?;
+        for i in 0..doc.n_pages() {
+            cancellable.set_error_if_cancelled()?;
+            let page = doc.page(i).expect("out of range");
+            let text = page.text();
+            f(Some(i as u64 + 1), text.as_deref().unwrap_or_default())?;
         }
+        Ok(())
+    }
+
+    fn is_cacheable(&self) -> bool {
+        true
+    }
+
+    fn error_kind(&self) -> ErrorKind {
+        ErrorKind::CorruptPdf
     }
-    Ok(())
 }
@@ -0,0 +1,103 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+use zip::ZipArchive;
+
+pub const PDF: &str = "application/pdf";
+pub const DOCX: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+pub const XLSX: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+pub const PPTX: &str = "application/vnd.openxmlformats-officedocument.presentationml.presentation";
+pub const ODT: &str = "application/vnd.oasis.opendocument.text";
+pub const ODS: &str = "application/vnd.oasis.opendocument.spreadsheet";
+pub const ODP: &str = "application/vnd.oasis.opendocument.presentation";
+
+/// The main part of each Office Open XML document type.
+const OOXML_PARTS: [(&str, &str); 3] = [
+    ("word/document.xml", DOCX),
+    ("xl/workbook.xml", XLSX),
+    ("ppt/presentation.xml", PPTX),
+];
+
+/// Detects the MIME type of a document by its content,
+/// for files whose name does not tell their format.
+pub fn mime_type(path: &Path) -> Option<&'static str> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0; 5];
+    file.read_exact(&mut magic).ok()?;
+
+    match &magic {
+        b"%PDF-" => Some(PDF),
+        [b'P', b'K', 3, 4, _] => {
+            file.seek(SeekFrom::Start(0)).ok()?;
+            zip_mime_type(ZipArchive::new(file).ok()?)
+        }
+        _ => None,
+    }
+}
+
+fn zip_mime_type(mut archive: ZipArchive<File>) -> Option<&'static str> {
+    // OpenDocument files name their type in an uncompressed entry.
+    if let Ok(entry) = archive.by_name("mimetype") {
+        let mut mime_type = String::new();
+        entry.take(128).read_to_string(&mut mime_type).ok()?;
+        return [ODT, ODS, ODP]
+            .into_iter()
+            .find(|known| *known == mime_type.trim());
+    }
+
+    archive.index_for_name("[Content_Types].xml")?;
+    OOXML_PARTS
+        .iter()
+        .find(|(part, _)| archive.index_for_name(part).is_some())
+        .map(|(_, mime_type)| *mime_type)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mime_type, DOCX, ODS, PDF};
+    use crate::testing::TempDir;
+    use std::{fs, fs::File, io::Write, path::PathBuf};
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    fn zip(path: &PathBuf, entries: &[(&str, &str)]) {
+        let mut writer = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_mime_type() {
+        let dir = TempDir::new("sniff");
+
+        let pdf = dir.join("report");
+        fs::write(&pdf, "%PDF-1.7\n").unwrap();
+        let docx = dir.join("letter.DOCX");
+        zip(
+            &docx,
+            &[
+                ("[Content_Types].xml", "<Types/>"),
+                ("word/document.xml", ""),
+            ],
+        );
+        let ods = dir.join("table");
+        zip(
+            &ods,
+            &[("mimetype", "application/vnd.oasis.opendocument.spreadsheet")],
+        );
+        let other = dir.join("other.zip");
+        zip(&other, &[("notes.txt", "some notes")]);
+
+        assert_eq!(mime_type(&pdf), Some(PDF));
+        assert_eq!(mime_type(&docx), Some(DOCX));
+        assert_eq!(mime_type(&ods), Some(ODS));
+        assert_eq!(mime_type(&other), None);
+        assert_eq!(mime_type(&dir.join("missing")), None);
+    }
+}
@@ -0,0 +1,180 @@
+use ignore::types::{Types, TypesBuilder};
+use std::path::Path;
+
+pub const PDF: &str = "PDF";
+pub const DOCUMENTS: &str = "Documents";
+pub const SPREADSHEETS: &str = "Spreadsheets";
+pub const PRESENTATIONS: &str = "Presentations";
+pub const COMPRESSED: &str = "Compressed";
+pub const ARCHIVES: &str = "Archives";
+
+/// The types which are searched with `extra::office`.
+pub const OFFICE: &[&str] = &[DOCUMENTS, SPREADSHEETS, PRESENTATIONS];
+
+const BUILTIN: &[(&str, &[&str])] = &[
+    (
+        "C/C++",
+        &["*.c", "*.h", "*.cc", "*.cpp", "*.cxx", "*.hh", "*.hpp"],
+    ),
+    ("C#", &["*.cs"]),
+    ("CSS", &["*.css", "*.scss", "*.sass", "*.less"]),
+    ("Go", &["*.go"]),
+    ("HTML", &["*.html", "*.htm", "*.xhtml"]),
+    ("Java", &["*.java"]),
+    ("JavaScript", &["*.js", "*.mjs", "*.cjs", "*.jsx"]),
+    ("JSON", &["*.json"]),
+    ("Markdown", &["*.md", "*.markdown", "*.mdx"]),
+    ("Python", &["*.py", "*.pyi", "*.pyw"]),
+    ("Rust", &["*.rs"]),
+    ("Shell", &["*.sh", "*.bash", "*.zsh", "*.fish"]),
+    ("Text", &["*.txt"]),
+    ("TOML", &["*.toml"]),
+    ("TypeScript", &["*.ts", "*.tsx", "*.mts", "*.cts"]),
+    ("XML", &["*.xml"]),
+    ("YAML", &["*.yaml", "*.yml"]),
+    (PDF, &["*.pdf"]),
+    (DOCUMENTS, &["*.docx", "*.odt"]),
+    (SPREADSHEETS, &["*.xlsx", "*.ods"]),
+    (PRESENTATIONS, &["*.pptx", "*.odp"]),
+    (COMPRESSED, &["*.gz", "*.xz", "*.bz2", "*.zst"]),
+    (
+        ARCHIVES,
+        &[
+            "*.zip",
+            "*.tar",
+            "*.tar.gz",
+            "*.tgz",
+            "*.tar.xz",
+            "*.tar.bz2",
+            "*.tar.zst",
+        ],
+    ),
+];
+
+/// A named set of globs, like the file types of ripgrep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileType {
+    pub name: String,
+    pub globs: Vec<String>,
+}
+
+impl FileType {
+    pub fn new(
+        name: impl Into<String>,
+        globs: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        FileType {
+            name: name.into(),
+            globs: globs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// The registry of known file types.
+#[derive(Debug, Clone)]
+pub struct FileTypes {
+    types: Vec<FileType>,
+}
+
+impl Default for FileTypes {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl FileTypes {
+    pub fn builtin() -> FileTypes {
+        FileTypes {
+            types: BUILTIN
+                .iter()
+                .map(|(name, globs)| FileType::new(*name, globs.iter().copied()))
+                .collect(),
+        }
+    }
+
+    /// Adds a user defined type, replacing a type with the same name.
+    pub fn add(&mut self, file_type: FileType) {
+        match self.types.iter_mut().find(|t| t.name == file_type.name) {
+            Some(existing) => *existing = file_type,
+            None => self.types.push(file_type),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&FileType> {
+        self.types.iter().find(|t| t.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FileType> {
+        self.types.iter()
+    }
+
+    /// Builds a matcher for the types with the given names.
+    pub fn matcher(&self, names: &[&str]) -> Result<FileTypeMatcher, ignore::Error> {
+        let selected = names
+            .iter()
+            .filter_map(|name| self.get(name))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        FileTypeMatcher::new(&selected)
+    }
+}
+
+/// Matches the files belonging to any of a set of types.
+#[derive(Debug, Clone)]
+pub struct FileTypeMatcher(Types);
+
+impl FileTypeMatcher {
+    pub fn new(types: &[FileType]) -> Result<FileTypeMatcher, ignore::Error> {
+        let mut builder = TypesBuilder::new();
+
+        // The names of types in `ignore` have to be alphanumeric.
+        for (i, file_type) in types.iter().enumerate() {
+            let key = format!("type{i}");
+            for glob in &file_type.globs {
+                builder.add(&key, glob)?;
+            }
+            builder.select(&key);
+        }
+
+        builder.build().map(FileTypeMatcher)
+    }
+
+    pub fn is_match(&self, path: &Path) -> bool {
+        self.0.matched(path, false).is_whitelist()
+    }
+
+    /// Whether a file is searched, which is every file if no types are selected.
+    pub fn allows(&self, path: &Path) -> bool {
+        !self.0.matched(path, false).is_ignore()
+    }
+
+    pub(crate) fn into_types(self) -> Types {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{FileType, FileTypes, OFFICE, PDF};
+    use std::path::Path;
+
+    #[test]
+    fn test_file_type_matcher() {
+        let mut types = FileTypes::builtin();
+        types.add(FileType::new("Logs", ["*.log", "*.log.[0-9]"]));
+
+        let matcher = types.matcher(&["Python", "Logs"]).unwrap();
+        assert!(matcher.is_match(Path::new("src/main.py")));
+        assert!(matcher.is_match(Path::new("/var/log/app.log.1")));
+        assert!(!matcher.is_match(Path::new("README.md")));
+
+        let documents = types.matcher(OFFICE).unwrap();
+        assert!(documents.is_match(Path::new("slides.odp")));
+        assert!(!documents.is_match(Path::new("paper.pdf")));
+        assert!(types
+            .matcher(&[PDF])
+            .unwrap()
+            .is_match(Path::new("paper.pdf")));
+    }
+}
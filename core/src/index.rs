@@ -0,0 +1,429 @@
+use crate::{
+    extra::{ExtractedText, ExtractorRegistry},
+    utils, SearchFlags, SearchTerm,
+};
+use gio::prelude::CancellableExt;
+use ignore::{WalkBuilder, WalkState};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs::{self, File, Metadata},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+
+const MAGIC: &[u8; 16] = b"CLAPGREP-INDEX-1";
+
+/// The trigrams of the text extracted from documents, which lets searches skip
+/// documents that cannot contain a pattern without extracting them again.
+///
+/// The trigrams of each document are kept in a bloom filter,
+/// so the index can only tell where a pattern is not found.
+#[derive(Default)]
+pub struct Index {
+    documents: HashMap<PathBuf, Document>,
+}
+
+#[derive(Clone)]
+struct Document {
+    /// Time of the last modification since the Unix epoch.
+    modified: Duration,
+    size: u64,
+    trigrams: Trigrams,
+}
+
+/// A bloom filter with a single hash, whose size grows with the number of trigrams.
+#[derive(Clone)]
+struct Trigrams {
+    log2_bits: u8,
+    bits: Vec<u64>,
+}
+
+impl Index {
+    /// The file in a cache directory which stores the index of a directory.
+    pub fn file(cache_dir: &Path, root: &Path) -> PathBuf {
+        let hash = utils::stable_hash(root.to_string_lossy().as_bytes());
+        cache_dir.join(format!("{hash:016x}.index"))
+    }
+
+    pub fn load(file: &Path) -> io::Result<Index> {
+        let mut reader = BufReader::new(File::open(file)?);
+
+        let mut magic = [0; 16];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an index of this version",
+            ));
+        }
+
+        let count = read_u64(&mut reader)?;
+        let mut documents = HashMap::new();
+        for _ in 0..count {
+            let mut path = vec![0; read_u32(&mut reader)? as usize];
+            reader.read_exact(&mut path)?;
+            let path = String::from_utf8(path)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            let modified = Duration::new(read_u64(&mut reader)?, read_u32(&mut reader)?);
+            let size = read_u64(&mut reader)?;
+
+            let mut log2_bits = [0];
+            reader.read_exact(&mut log2_bits)?;
+            let log2_bits = log2_bits[0];
+            if !(6..=24).contains(&log2_bits) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid size of trigrams",
+                ));
+            }
+            let bits = (0..1usize << (log2_bits - 6))
+                .map(|_| read_u64(&mut reader))
+                .collect::<io::Result<_>>()?;
+
+            let trigrams = Trigrams { log2_bits, bits };
+            documents.insert(
+                PathBuf::from(path),
+                Document {
+                    modified,
+                    size,
+                    trigrams,
+                },
+            );
+        }
+
+        Ok(Index { documents })
+    }
+
+    /// Saves the index, replacing the previous file only once it is complete.
+    pub fn save(&self, file: &Path) -> io::Result<()> {
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Searches of the same directory might save the index at the same time.
+        let temp = utils::temp_path(file);
+        self.write(&temp)
+            .and_then(|_| fs::rename(&temp, file))
+            .inspect_err(|_| {
+                _ = fs::remove_file(&temp);
+            })
+    }
+
+    fn write(&self, file: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(file)?);
+
+        // Paths which are not valid UTF-8 are not indexed.
+        let documents = self
+            .documents
+            .iter()
+            .filter_map(|(path, document)| Some((path.to_str()?, document)))
+            .collect::<Vec<_>>();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&(documents.len() as u64).to_le_bytes())?;
+        for (path, document) in documents {
+            writer.write_all(&(path.len() as u32).to_le_bytes())?;
+            writer.write_all(path.as_bytes())?;
+            writer.write_all(&document.modified.as_secs().to_le_bytes())?;
+            writer.write_all(&document.modified.subsec_nanos().to_le_bytes())?;
+            writer.write_all(&document.size.to_le_bytes())?;
+            writer.write_all(&[document.trigrams.log2_bits])?;
+            for word in &document.trigrams.bits {
+                writer.write_all(&word.to_le_bytes())?;
+            }
+        }
+
+        writer.into_inner()?.sync_all()
+    }
+
+    /// The number of indexed documents.
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Indexes the documents below a directory and forgets all others,
+    /// extracting only the documents which changed since they were indexed.
+    pub fn update(
+        &mut self,
+        root: &Path,
+        extractors: &ExtractorRegistry,
+        cancellable: &gio::Cancellable,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut extractors = extractors.clone();
+        extractors.add_builtin(true, true);
+
+        let documents = Mutex::new(HashMap::new());
+        let (old, extractors, new) = (&*self, &extractors, &documents);
+        WalkBuilder::new(root).build_parallel().run(|| {
+            Box::new(move |entry| {
+                if cancellable.is_cancelled() {
+                    return WalkState::Quit;
+                }
+
+                let Ok(entry) = entry else {
+                    return WalkState::Continue;
+                };
+                let Ok(metadata) = entry.metadata() else {
+                    return WalkState::Continue;
+                };
+                if !metadata.is_file() {
+                    return WalkState::Continue;
+                }
+                // Command output can change without the file changing.
+                let Some(extractor) = extractors
+                    .detect(entry.path())
+                    .filter(|extractor| extractor.is_cacheable())
+                else {
+                    return WalkState::Continue;
+                };
+
+                let document = match old.fresh(entry.path(), &metadata) {
+                    Some(document) => document.clone(),
+                    None => match extractor.extract(entry.path(), cancellable) {
+                        Ok(text) => Document::new(&metadata, &text),
+                        // Documents which cannot be extracted are reported by searches.
+                        Err(_) => return WalkState::Continue,
+                    },
+                };
+
+                new.lock()
+                    .unwrap()
+                    .insert(entry.path().to_path_buf(), document);
+                WalkState::Continue
+            })
+        });
+
+        cancellable.set_error_if_cancelled()?;
+        self.documents = documents.into_inner().unwrap();
+        Ok(())
+    }
+
+    /// The document at a path, unless it changed since it was indexed.
+    fn fresh(&self, path: &Path, metadata: &Metadata) -> Option<&Document> {
+        self.documents.get(path).filter(|document| {
+            document.size == metadata.len() && Some(document.modified) == modified(metadata)
+        })
+    }
+
+    fn insert(&mut self, path: &Path, metadata: &Metadata, text: &ExtractedText) {
+        self.documents
+            .insert(path.to_path_buf(), Document::new(metadata, text));
+    }
+}
+
+impl Document {
+    fn new(metadata: &Metadata, text: &ExtractedText) -> Document {
+        let trigrams = match text {
+            ExtractedText::Text(text) => Trigrams::new(text),
+            ExtractedText::Pages(pages) => Trigrams::new(&pages.join("\n")),
+        };
+
+        Document {
+            modified: modified(metadata).unwrap_or_default(),
+            size: metadata.len(),
+            trigrams,
+        }
+    }
+}
+
+impl Trigrams {
+    fn new(text: &str) -> Trigrams {
+        let trigrams = trigrams(&normalize(text)).collect::<HashSet<_>>();
+
+        // About a fifth of the bits are set, which keeps false positives rare
+        // for patterns with a few trigrams.
+        let log2_bits = (trigrams.len() * 4)
+            .clamp(64, 1 << 24)
+            .next_power_of_two()
+            .trailing_zeros() as u8;
+
+        let mut bits = vec![0; 1 << (log2_bits - 6)];
+        for trigram in trigrams {
+            let bit = bucket(trigram, log2_bits);
+            bits[bit / 64] |= 1 << (bit % 64);
+        }
+
+        Trigrams { log2_bits, bits }
+    }
+
+    fn contains_all(&self, trigrams: &[u32]) -> bool {
+        trigrams.iter().all(|&trigram| {
+            let bit = bucket(trigram, self.log2_bits);
+            self.bits[bit / 64] & (1 << (bit % 64)) != 0
+        })
+    }
+}
+
+/// Fibonacci hashing, which spreads the trigrams of similar texts.
+fn bucket(trigram: u32, log2_bits: u8) -> usize {
+    (trigram.wrapping_mul(0x9e37_79b9) as u64 >> (32 - log2_bits)) as usize
+}
+
+/// Lowercases text character by character, so that a lowercased pattern is
+/// contained in the lowercased text whenever it matches case insensitively.
+///
+/// The long s is the only character besides ASCII letters
+/// that matches an ASCII letter when ignoring case.
+fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c == 'ſ' { 's' } else { c })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn trigrams(text: &str) -> impl Iterator<Item = u32> + '_ {
+    text.as_bytes()
+        .windows(3)
+        .map(|w| u32::from_le_bytes([w[0], w[1], w[2], 0]))
+}
+
+/// The trigrams every document matching the terms contains,
+/// taken from the included terms without any regex syntax.
+pub(crate) fn required_trigrams(terms: &[SearchTerm], flags: &SearchFlags) -> Vec<u32> {
+    if flags.invert {
+        return Vec::new();
+    }
+
+    // Other characters might match different characters when ignoring case.
+    let case_sensitive = flags.case_sensitive && !flags.smart_case;
+
+    let mut required = terms
+        .iter()
+        .filter(|term| !term.exclude)
+        .filter(|term| flags.fixed_string || regex::escape(&term.pattern) == term.pattern)
+        .filter(|term| case_sensitive || term.pattern.is_ascii())
+        .flat_map(|term| trigrams(&normalize(&term.pattern)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    required.sort_unstable();
+    required.dedup();
+    required
+}
+
+/// An index which is used and updated by the threads of a search.
+pub(crate) struct SearchIndex {
+    file: PathBuf,
+    index: Mutex<Index>,
+    required: Vec<u32>,
+    changed: AtomicBool,
+}
+
+impl SearchIndex {
+    pub fn open(file: PathBuf, required: Vec<u32>) -> SearchIndex {
+        let index = match Index::load(&file) {
+            Ok(index) => index,
+            Err(err) => {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::warn!("failed to load index {}: {err}", file.display());
+                }
+                Index::default()
+            }
+        };
+
+        SearchIndex {
+            file,
+            index: Mutex::new(index),
+            required,
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether a document cannot match, which is known if it did not change since it was indexed.
+    pub fn rules_out(&self, path: &Path, metadata: &Metadata) -> bool {
+        !self.required.is_empty()
+            && self
+                .index
+                .lock()
+                .unwrap()
+                .fresh(path, metadata)
+                .is_some_and(|document| !document.trigrams.contains_all(&self.required))
+    }
+
+    /// Adds a document, unless it is already indexed.
+    pub fn insert(&self, path: &Path, metadata: &Metadata, text: &ExtractedText) {
+        let mut index = self.index.lock().unwrap();
+        if index.fresh(path, metadata).is_none() {
+            index.insert(path, metadata, text);
+            self.changed.store(true, Ordering::Relaxed);
+        }
+    }
+
+    /// Saves the index if documents were added to it.
+    pub fn save(&self) {
+        if !self.changed.load(Ordering::Relaxed) {
+            return;
+        }
+
+        if let Err(err) = self.index.lock().unwrap().save(&self.file) {
+            log::warn!("failed to save index {}: {err}", self.file.display());
+        }
+    }
+}
+
+fn modified(metadata: &Metadata) -> Option<Duration> {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{required_trigrams, Index, SearchIndex};
+    use crate::{extra::ExtractedText, testing::TempDir, SearchFlags, SearchTerm};
+    use std::fs;
+
+    #[test]
+    fn test_index() {
+        let dir = TempDir::new("index");
+        let document = dir.join("report.pdf");
+        fs::write(&document, "not really a pdf").unwrap();
+        let metadata = fs::metadata(&document).unwrap();
+
+        let file = Index::file(dir.path(), dir.path());
+        let flags = SearchFlags::default();
+        let search = |pattern: &str| {
+            let required = required_trigrams(&[SearchTerm::include(pattern)], &flags);
+            SearchIndex::open(file.clone(), required)
+        };
+
+        let index = search("anything");
+        assert!(!index.rules_out(&document, &metadata));
+        let text = ExtractedText::Pages(vec!["Quarterly Report".into(), "Revenue".into()]);
+        index.insert(&document, &metadata, &text);
+        index.save();
+
+        assert!(!search("quarterly").rules_out(&document, &metadata));
+        assert!(!search("REVENUE").rules_out(&document, &metadata));
+        assert!(search("profit").rules_out(&document, &metadata));
+        // Only literal patterns can rule out documents.
+        assert!(!search("pro.it").rules_out(&document, &metadata));
+
+        // Changed documents have to be searched again.
+        fs::write(&document, "changed").unwrap();
+        let changed = fs::metadata(&document).unwrap();
+        assert!(!search("profit").rules_out(&document, &changed));
+    }
+}
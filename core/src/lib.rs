@@ -1,12 +1,33 @@
+mod encoding;
 mod engine;
+mod index;
+mod matcher;
+mod paths;
+mod progress;
+mod query;
 mod result;
 mod search;
+mod sort;
+mod stats;
 mod utils;
 
+#[cfg(test)]
+mod testing;
+
 pub mod extra;
+pub mod file_types;
+pub mod replace;
 
-pub use engine::SearchEngine;
-pub use result::{Location, ResultEntry, SearchMessage, SearchResult};
-pub use search::{SearchFlags, SearchParameters};
+pub use encoding::{decode, FALLBACK_ENCODINGS};
+pub use engine::{SearchEngine, SearchHandle};
+pub use index::Index;
+pub use matcher::validate_pattern;
+pub use paths::{split_path_patterns, validate_path_patterns};
+pub use progress::SearchProgress;
+pub use replace::Edit;
+pub use result::{ErrorKind, Location, ResultEntry, SearchError, SearchMessage, SearchResult};
+pub use search::{SearchFilters, SearchFlags, SearchParameters, SearchTerm};
+pub use sort::SortOrder;
+pub use stats::{SearchStats, SkippedFiles};
 
 pub use grep::matcher::Match;
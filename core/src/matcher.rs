@@ -0,0 +1,177 @@
+use crate::SearchFlags;
+use grep::{
+    matcher::{ByteSet, Captures, LineMatchKind, LineTerminator, Match, Matcher},
+    pcre2, regex,
+};
+use std::error::Error;
+
+pub type MatcherError = Box<dyn Error + Send + Sync>;
+
+/// Checks whether a pattern can be searched for with the given flags.
+pub fn validate_pattern(pattern: &str, flags: &SearchFlags) -> Result<(), String> {
+    MatcherBuilder::new(flags)
+        .build(pattern)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+/// Builds matchers for the regex engine selected by the search flags.
+#[derive(Clone)]
+pub enum MatcherBuilder {
+    Rust(regex::RegexMatcherBuilder),
+    Pcre2(pcre2::RegexMatcherBuilder),
+}
+
+impl MatcherBuilder {
+    pub fn new(flags: &SearchFlags) -> MatcherBuilder {
+        let case_insensitive = !flags.case_sensitive && !flags.smart_case;
+
+        if flags.pcre2 {
+            let mut builder = pcre2::RegexMatcherBuilder::new();
+            builder
+                .caseless(case_insensitive)
+                .case_smart(flags.smart_case)
+                .fixed_strings(flags.fixed_string)
+                .word(flags.whole_word)
+                .multi_line(flags.multiline)
+                .utf(true)
+                .ucp(true)
+                .jit_if_available(true);
+            MatcherBuilder::Pcre2(builder)
+        } else {
+            let mut builder = regex::RegexMatcherBuilder::new();
+            builder
+                .case_insensitive(case_insensitive)
+                .case_smart(flags.smart_case)
+                .fixed_strings(flags.fixed_string)
+                .word(flags.whole_word)
+                .multi_line(flags.multiline);
+            MatcherBuilder::Rust(builder)
+        }
+    }
+
+    pub fn build(&self, pattern: &str) -> Result<SearchMatcher, MatcherError> {
+        Ok(match self {
+            MatcherBuilder::Rust(builder) => SearchMatcher::Rust(builder.build(pattern)?),
+            MatcherBuilder::Pcre2(builder) => SearchMatcher::Pcre2(builder.build(pattern)?),
+        })
+    }
+
+    /// Builds a matcher which matches any of the given patterns.
+    pub fn build_many(&self, patterns: &[&str]) -> Result<SearchMatcher, MatcherError> {
+        Ok(match self {
+            MatcherBuilder::Rust(builder) => SearchMatcher::Rust(builder.build_many(patterns)?),
+            MatcherBuilder::Pcre2(builder) => SearchMatcher::Pcre2(builder.build_many(patterns)?),
+        })
+    }
+}
+
+/// A matcher backed by either Rust's regex crate or PCRE2.
+///
+/// PCRE2 supports look-around and backreferences,
+/// which are not available with Rust's regex crate.
+#[derive(Clone, Debug)]
+pub enum SearchMatcher {
+    Rust(regex::RegexMatcher),
+    Pcre2(pcre2::RegexMatcher),
+}
+
+#[derive(Clone, Debug)]
+pub enum SearchCaptures {
+    Rust(regex::RegexCaptures),
+    Pcre2(pcre2::RegexCaptures),
+}
+
+impl Captures for SearchCaptures {
+    fn len(&self) -> usize {
+        match self {
+            SearchCaptures::Rust(caps) => caps.len(),
+            SearchCaptures::Pcre2(caps) => caps.len(),
+        }
+    }
+
+    fn get(&self, i: usize) -> Option<Match> {
+        match self {
+            SearchCaptures::Rust(caps) => caps.get(i),
+            SearchCaptures::Pcre2(caps) => caps.get(i),
+        }
+    }
+}
+
+impl Matcher for SearchMatcher {
+    type Captures = SearchCaptures;
+    type Error = MatcherError;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, Self::Error> {
+        match self {
+            SearchMatcher::Rust(m) => Ok(m.find_at(haystack, at)?),
+            SearchMatcher::Pcre2(m) => Ok(m.find_at(haystack, at)?),
+        }
+    }
+
+    fn new_captures(&self) -> Result<Self::Captures, Self::Error> {
+        match self {
+            SearchMatcher::Rust(m) => Ok(SearchCaptures::Rust(m.new_captures()?)),
+            SearchMatcher::Pcre2(m) => Ok(SearchCaptures::Pcre2(m.new_captures()?)),
+        }
+    }
+
+    fn capture_count(&self) -> usize {
+        match self {
+            SearchMatcher::Rust(m) => m.capture_count(),
+            SearchMatcher::Pcre2(m) => m.capture_count(),
+        }
+    }
+
+    fn capture_index(&self, name: &str) -> Option<usize> {
+        match self {
+            SearchMatcher::Rust(m) => m.capture_index(name),
+            SearchMatcher::Pcre2(m) => m.capture_index(name),
+        }
+    }
+
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut Self::Captures,
+    ) -> Result<bool, Self::Error> {
+        match (self, caps) {
+            (SearchMatcher::Rust(m), SearchCaptures::Rust(caps)) => {
+                Ok(m.captures_at(haystack, at, caps)?)
+            }
+            (SearchMatcher::Pcre2(m), SearchCaptures::Pcre2(caps)) => {
+                Ok(m.captures_at(haystack, at, caps)?)
+            }
+            _ => Err("captures do not belong to this matcher".into()),
+        }
+    }
+
+    fn shortest_match_at(&self, haystack: &[u8], at: usize) -> Result<Option<usize>, Self::Error> {
+        match self {
+            SearchMatcher::Rust(m) => Ok(m.shortest_match_at(haystack, at)?),
+            SearchMatcher::Pcre2(m) => Ok(m.shortest_match_at(haystack, at)?),
+        }
+    }
+
+    fn non_matching_bytes(&self) -> Option<&ByteSet> {
+        match self {
+            SearchMatcher::Rust(m) => m.non_matching_bytes(),
+            SearchMatcher::Pcre2(m) => m.non_matching_bytes(),
+        }
+    }
+
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        match self {
+            SearchMatcher::Rust(m) => m.line_terminator(),
+            SearchMatcher::Pcre2(m) => m.line_terminator(),
+        }
+    }
+
+    fn find_candidate_line(&self, haystack: &[u8]) -> Result<Option<LineMatchKind>, Self::Error> {
+        match self {
+            SearchMatcher::Rust(m) => Ok(m.find_candidate_line(haystack)?),
+            SearchMatcher::Pcre2(m) => Ok(m.find_candidate_line(haystack)?),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use ignore::overrides::{Override, OverrideBuilder};
+use std::path::Path;
+
+/// Splits a comma separated list of globs,
+/// keeping commas inside of `{a,b}` alternations.
+pub fn split_path_patterns(text: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for c in text.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                patterns.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    patterns.push(current);
+
+    patterns
+        .into_iter()
+        .map(|pattern| pattern.trim().to_string())
+        .filter(|pattern| !pattern.is_empty())
+        .collect()
+}
+
+/// Builds the overrides which decide which paths are searched.
+///
+/// Globs have the same semantics as lines in a `.gitignore` file, except
+/// that globs prefixed with `!` exclude paths instead of including them.
+/// If `explicit` is set, globs have to match the whole path relative
+/// to `base_directory` instead of any file name.
+///
+/// The overrides take precedence over the file types: paths matching an
+/// include glob are searched and paths matching an exclude glob are not,
+/// whatever their type. As soon as there is an include glob, all other
+/// files are skipped too. With only exclude globs, the file types decide
+/// about all other files.
+pub fn path_overrides(
+    base_directory: &Path,
+    patterns: &[String],
+    explicit: bool,
+) -> Result<Override, ignore::Error> {
+    let mut builder = OverrideBuilder::new(base_directory);
+    builder.case_insensitive(true)?;
+
+    for pattern in patterns {
+        if explicit {
+            let (negation, glob) = match pattern.strip_prefix('!') {
+                Some(glob) => ("!", glob),
+                None => ("", pattern.as_str()),
+            };
+            builder.add(&format!("{negation}/{}", glob.trim_start_matches('/')))?;
+        } else {
+            builder.add(pattern)?;
+        }
+    }
+
+    builder.build()
+}
+
+/// Checks that all globs are valid, describing the first invalid one otherwise.
+pub fn validate_path_patterns(patterns: &[String]) -> Result<(), String> {
+    path_overrides(Path::new(""), patterns, false)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{path_overrides, split_path_patterns};
+    use std::path::Path;
+
+    #[test]
+    fn test_split_path_patterns() {
+        let patterns = split_path_patterns("*.rs, *.{toml,lock} ,, !target/");
+        assert_eq!(patterns, ["*.rs", "*.{toml,lock}", "!target/"]);
+    }
+
+    #[test]
+    fn test_path_overrides() {
+        let patterns = split_path_patterns("*.rs, *.TOML, !target/");
+        let overrides = path_overrides(Path::new("/repo"), &patterns, false).unwrap();
+
+        assert!(overrides.matched("/repo/src/main.rs", false).is_whitelist());
+        assert!(overrides.matched("/repo/Cargo.toml", false).is_whitelist());
+        assert!(overrides.matched("/repo/README.md", false).is_ignore());
+        assert!(overrides.matched("/repo/target", true).is_ignore());
+
+        let explicit = path_overrides(Path::new("/repo"), &patterns, true).unwrap();
+        assert!(explicit.matched("/repo/main.rs", false).is_whitelist());
+        assert!(explicit.matched("/repo/src/main.rs", false).is_ignore());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    engine::SearchContext,
+    search::SearchId,
+    stats::{SearchStats, SkipReason, SkippedFiles},
+    SearchMessage,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Progress is reported at most this often.
+const REPORT_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchProgress {
+    pub search: SearchId,
+    /// Files found so far, which are all files to be searched
+    /// once `discovery_complete` is set.
+    pub files_discovered: u64,
+    pub discovery_complete: bool,
+    pub files_searched: u64,
+    /// Documents which were not searched because the index ruled them out.
+    pub files_ruled_out: u64,
+    /// Size of the searched files.
+    pub bytes_scanned: u64,
+    /// The file which was most recently started to be searched.
+    pub current_file: Option<PathBuf>,
+}
+
+/// Collects the progress and statistics of all walker threads.
+pub(crate) struct ProgressTracker {
+    context: SearchContext,
+    started: Instant,
+    files_discovered: AtomicU64,
+    discovery_complete: AtomicBool,
+    files_searched: AtomicU64,
+    bytes_scanned: AtomicU64,
+    matches: AtomicU64,
+    /// Files which would be searched if it were not for ignore files.
+    files_not_ignored: AtomicU64,
+    skipped: Mutex<SkippedFiles>,
+    current_file: Mutex<Option<PathBuf>>,
+    last_report: Mutex<Option<Instant>>,
+}
+
+impl ProgressTracker {
+    pub fn new(context: SearchContext) -> Self {
+        ProgressTracker {
+            context,
+            started: Instant::now(),
+            files_discovered: AtomicU64::new(0),
+            discovery_complete: AtomicBool::new(false),
+            files_searched: AtomicU64::new(0),
+            bytes_scanned: AtomicU64::new(0),
+            matches: AtomicU64::new(0),
+            files_not_ignored: AtomicU64::new(0),
+            skipped: Mutex::new(SkippedFiles::default()),
+            current_file: Mutex::new(None),
+            last_report: Mutex::new(None),
+        }
+    }
+
+    pub fn discovered(&self) {
+        self.files_discovered.fetch_add(1, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    pub fn discovery_completed(&self) {
+        self.discovery_complete.store(true, Ordering::Relaxed);
+        self.report(false);
+    }
+
+    pub fn skipped(&self, reason: SkipReason) {
+        self.skipped.lock().unwrap().add(reason);
+    }
+
+    pub fn not_ignored(&self) {
+        self.files_not_ignored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn found_matches(&self, matches: u64) {
+        self.matches.fetch_add(matches, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> SearchStats {
+        let files_searched = self.files_searched.load(Ordering::Relaxed);
+        let mut skipped = self.skipped.lock().unwrap().clone();
+        let files_walked = files_searched + skipped.ruled_out;
+        skipped.ignored = self
+            .files_not_ignored
+            .load(Ordering::Relaxed)
+            .saturating_sub(files_walked);
+
+        SearchStats {
+            elapsed: self.started.elapsed(),
+            files_walked,
+            files_searched: files_searched.saturating_sub(skipped.binary),
+            bytes_read: self.bytes_scanned.load(Ordering::Relaxed),
+            matches: self.matches.load(Ordering::Relaxed),
+            skipped,
+        }
+    }
+
+    /// Marks a file as being searched, until the returned guard is dropped.
+    pub fn searching(&self, path: &Path, bytes: u64) -> Searching<'_> {
+        *self.current_file.lock().unwrap() = Some(path.to_path_buf());
+        self.report(false);
+
+        Searching {
+            tracker: self,
+            bytes,
+        }
+    }
+
+    /// Sends the current progress, unless it was sent recently.
+    ///
+    /// A forced report waits for room in the channel,
+    /// while others are dropped if the channel is full.
+    pub fn report(&self, force: bool) {
+        {
+            let mut last_report = self.last_report.lock().unwrap();
+            if !force && last_report.is_some_and(|last| last.elapsed() < REPORT_INTERVAL) {
+                return;
+            }
+            *last_report = Some(Instant::now());
+        }
+
+        let message = SearchMessage::Progress(SearchProgress {
+            search: self.context.id(),
+            files_discovered: self.files_discovered.load(Ordering::Relaxed),
+            discovery_complete: self.discovery_complete.load(Ordering::Relaxed),
+            files_searched: self.files_searched.load(Ordering::Relaxed),
+            files_ruled_out: self.skipped.lock().unwrap().ruled_out,
+            bytes_scanned: self.bytes_scanned.load(Ordering::Relaxed),
+            current_file: self.current_file.lock().unwrap().clone(),
+        });
+
+        if force {
+            self.context.send(message);
+        } else {
+            self.context.try_send(message);
+        }
+    }
+}
+
+pub(crate) struct Searching<'a> {
+    tracker: &'a ProgressTracker,
+    bytes: u64,
+}
+
+impl Drop for Searching<'_> {
+    fn drop(&mut self) {
+        self.tracker.files_searched.fetch_add(1, Ordering::Relaxed);
+        self.tracker
+            .bytes_scanned
+            .fetch_add(self.bytes, Ordering::Relaxed);
+    }
+}
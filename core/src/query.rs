@@ -0,0 +1,114 @@
+use crate::{
+    matcher::{MatcherBuilder, MatcherError, SearchMatcher},
+    ResultEntry, SearchTerm,
+};
+use anyhow::{anyhow, bail};
+use grep::matcher::Matcher;
+
+/// A set of search terms, which is evaluated per file.
+///
+/// A file satisfies the query if every included term and none
+/// of the excluded terms matches somewhere within the file.
+#[derive(Clone)]
+pub struct Query {
+    /// Matches all terms, used to find the entries of a file.
+    pub matcher: SearchMatcher,
+    /// Matches only the included terms, used for file names.
+    pub include_matcher: SearchMatcher,
+    terms: Vec<(SearchMatcher, bool)>,
+}
+
+impl Query {
+    pub fn new(builder: &MatcherBuilder, terms: &[SearchTerm]) -> anyhow::Result<Query> {
+        let include = terms
+            .iter()
+            .filter(|term| !term.exclude)
+            .map(|term| term.pattern.as_str())
+            .collect::<Vec<_>>();
+
+        if include.is_empty() {
+            bail!("at least one included pattern is required");
+        }
+
+        let all = terms
+            .iter()
+            .map(|term| term.pattern.as_str())
+            .collect::<Vec<_>>();
+
+        let terms = terms
+            .iter()
+            .map(|term| Ok((builder.build(&term.pattern)?, term.exclude)))
+            .collect::<Result<Vec<_>, MatcherError>>()
+            .map_err(|err| anyhow!(err))?;
+
+        Ok(Query {
+            matcher: builder.build_many(&all).map_err(|err| anyhow!(err))?,
+            include_matcher: builder.build_many(&include).map_err(|err| anyhow!(err))?,
+            terms,
+        })
+    }
+
+    /// Whether the query consists of just a single included term.
+    pub fn is_simple(&self) -> bool {
+        self.terms.len() == 1
+    }
+
+    /// Whether a file with the given entries satisfies the query.
+    ///
+    /// The entries have to be found using `self.matcher`.
+    pub fn is_satisfied_by(&self, entries: &[ResultEntry]) -> bool {
+        if self.is_simple() {
+            return !entries.is_empty();
+        }
+
+        self.terms.iter().all(|(matcher, exclude)| {
+            let found = entries
+                .iter()
+                .any(|entry| matcher.is_match(entry.content.as_bytes()).unwrap_or(false));
+            found != *exclude
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Query;
+    use crate::{matcher::MatcherBuilder, Location, ResultEntry, SearchFlags, SearchTerm};
+
+    fn entry(content: &str) -> ResultEntry {
+        ResultEntry {
+            location: Location::Text { line: 1 },
+            end_line: 1,
+            content: content.to_string(),
+            matches: Vec::new(),
+            before_context: Vec::new(),
+            after_context: Vec::new(),
+            edit: None,
+        }
+    }
+
+    #[test]
+    fn test_query_evaluation() {
+        let terms = [
+            SearchTerm::include("invoice"),
+            SearchTerm::include("2024"),
+            SearchTerm::exclude("draft"),
+        ];
+        let query = Query::new(&MatcherBuilder::new(&SearchFlags::default()), &terms).unwrap();
+
+        let complete = [entry("invoice no. 42"), entry("date: 2024-01-01")];
+        assert!(query.is_satisfied_by(&complete));
+
+        let incomplete = [entry("invoice no. 42")];
+        assert!(!query.is_satisfied_by(&incomplete));
+
+        let excluded = [entry("invoice no. 42 (draft)"), entry("date: 2024-01-01")];
+        assert!(!query.is_satisfied_by(&excluded));
+    }
+
+    #[test]
+    fn test_query_requires_included_term() {
+        let terms = [SearchTerm::exclude("draft")];
+        assert!(Query::new(&MatcherBuilder::new(&SearchFlags::default()), &terms).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use crate::utils;
+use anyhow::{bail, Context};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A proposed replacement of the lines of a result entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edit {
+    /// Byte offset of the first replaced line in the file.
+    pub offset: u64,
+    /// The lines as they were found during the search.
+    pub original: Vec<u8>,
+    /// The lines with all matches replaced.
+    pub replacement: Vec<u8>,
+}
+
+/// The edits selected for a single file.
+#[derive(Debug, Clone)]
+pub struct FileEdits {
+    pub path: PathBuf,
+    pub edits: Vec<Edit>,
+}
+
+/// Replacements which have been written to disk,
+/// together with backups of the previous contents.
+#[derive(Debug)]
+pub struct ReplaceBatch {
+    files: Vec<BackedUpFile>,
+}
+
+/// A modified file with the backup of its previous content.
+#[derive(Debug)]
+struct BackedUpFile {
+    path: PathBuf,
+    backup: PathBuf,
+    /// Size and hash of the content written by the replacement,
+    /// which tell whether the file changed since.
+    size: u64,
+    hash: u64,
+}
+
+impl ReplaceBatch {
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|file| file.path.as_path())
+    }
+
+    /// Restores all files of the batch from their backups, or none of them
+    /// if any file changed since the replacement, so no later change is lost.
+    pub fn undo(self) -> anyhow::Result<()> {
+        for file in &self.files {
+            let content = fs::read(&file.path)
+                .with_context(|| format!("failed to read {}", file.path.display()))?;
+            if content.len() as u64 != file.size || utils::stable_hash(&content) != file.hash {
+                bail!("{} changed since the replacement", file.path.display());
+            }
+        }
+
+        for file in &self.files {
+            fs::read(&file.backup)
+                .and_then(|content| write_atomic(&file.path, &content))
+                .with_context(|| format!("failed to restore {}", file.path.display()))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies the edits to all files, or to none of them
+/// if any edit does not fit the current file contents.
+///
+/// The previous contents are kept in `backup_dir`,
+/// replacing the backups of the previous batch.
+pub fn apply(files: &[FileEdits], backup_dir: &Path) -> anyhow::Result<ReplaceBatch> {
+    let mut prepared = Vec::with_capacity(files.len());
+    for file in files {
+        // Symlinks are followed during the search, so they have to be here too.
+        let path = fs::canonicalize(&file.path)
+            .with_context(|| format!("failed to resolve {}", file.path.display()))?;
+        let content =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let replaced = apply_edits(&content, &file.edits)
+            .with_context(|| format!("failed to replace in {}", path.display()))?;
+        prepared.push((path, content, replaced));
+    }
+
+    if backup_dir.exists() {
+        fs::remove_dir_all(backup_dir).context("failed to remove previous backups")?;
+    }
+    fs::create_dir_all(backup_dir).context("failed to create backup directory")?;
+
+    let mut batch = ReplaceBatch { files: Vec::new() };
+    for (i, (path, content, replaced)) in prepared.iter().enumerate() {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let backup = backup_dir.join(format!("{i}-{file_name}"));
+        fs::write(&backup, content)
+            .with_context(|| format!("failed to back up {}", path.display()))?;
+        batch.files.push(BackedUpFile {
+            path: path.clone(),
+            backup,
+            size: replaced.len() as u64,
+            hash: utils::stable_hash(replaced),
+        });
+    }
+
+    for (i, (path, _, replaced)) in prepared.iter().enumerate() {
+        if let Err(err) = write_atomic(path, replaced) {
+            // Roll back the files which were already written.
+            for file in &batch.files[..i] {
+                _ = fs::read(&file.backup).and_then(|content| write_atomic(&file.path, &content));
+            }
+            return Err(err).with_context(|| format!("failed to write {}", path.display()));
+        }
+    }
+
+    Ok(batch)
+}
+
+/// Applies the edits to the content of a file.
+///
+/// Fails if an edit does not match the content,
+/// for example because the file changed since the search.
+pub fn apply_edits(content: &[u8], edits: &[Edit]) -> anyhow::Result<Vec<u8>> {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| edit.offset);
+
+    let mut result = Vec::with_capacity(content.len());
+    let mut last = 0;
+
+    for edit in edits {
+        let start = edit.offset as usize;
+        let end = start + edit.original.len();
+
+        if start < last {
+            bail!("overlapping edits at byte {start}");
+        }
+        if content.get(start..end) != Some(edit.original.as_slice()) {
+            bail!("content at byte {start} changed since the search");
+        }
+
+        result.extend_from_slice(&content[last..start]);
+        result.extend_from_slice(&edit.replacement);
+        last = end;
+    }
+
+    result.extend_from_slice(&content[last..]);
+    Ok(result)
+}
+
+/// Replaces the file by renaming a temporary file over it,
+/// so readers never observe a partially written file.
+fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let temp = utils::temp_path(path);
+    let permissions = fs::metadata(path)?.permissions();
+
+    fs::write(&temp, content)
+        .and_then(|_| fs::set_permissions(&temp, permissions))
+        .and_then(|_| fs::rename(&temp, path))
+        .inspect_err(|_| {
+            _ = fs::remove_file(&temp);
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{apply, apply_edits, Edit, FileEdits};
+    use crate::testing::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_apply_edits() {
+        let content = b"one\nfoo\ntwo\nfoo\n";
+        let edits = [
+            Edit {
+                offset: 12,
+                original: b"foo\n".to_vec(),
+                replacement: b"baz\n".to_vec(),
+            },
+            Edit {
+                offset: 4,
+                original: b"foo\n".to_vec(),
+                replacement: b"bar\n".to_vec(),
+            },
+        ];
+
+        let replaced = apply_edits(content, &edits).unwrap();
+        assert_eq!(replaced, b"one\nbar\ntwo\nbaz\n");
+
+        let changed = b"one\nfoo\n";
+        assert!(apply_edits(changed, &edits).is_err());
+    }
+
+    #[test]
+    fn test_undo_refuses_changed_files() {
+        let dir = TempDir::new("undo");
+        let path = dir.join("notes.txt");
+        let edits = || {
+            vec![FileEdits {
+                path: path.clone(),
+                edits: vec![Edit {
+                    offset: 0,
+                    original: b"foo\n".to_vec(),
+                    replacement: b"bar\n".to_vec(),
+                }],
+            }]
+        };
+
+        fs::write(&path, "foo\n").unwrap();
+        let batch = apply(&edits(), &dir.join("backup")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "bar\n");
+        batch.undo().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "foo\n");
+
+        let batch = apply(&edits(), &dir.join("backup")).unwrap();
+        fs::write(&path, "edited later\n").unwrap();
+        assert!(batch.undo().is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "edited later\n");
+    }
+}
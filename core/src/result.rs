@@ -2,7 +2,7 @@ use std::path::PathBuf;
 
 use grep::matcher::Match;
 
-use crate::search::SearchId;
+use crate::{progress::SearchProgress, replace::Edit, search::SearchId, stats::SearchStats};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Location {
@@ -12,33 +12,79 @@ pub enum Location {
 
 pub struct ResultEntry {
     pub location: Location,
+    /// The last line of the entry, which differs from the
+    /// line in `location` for multiline matches.
+    pub end_line: u64,
     pub content: String,
     pub matches: Vec<Match>,
+    /// Lines preceding the entry, if context was requested.
+    pub before_context: Vec<String>,
+    /// Lines following the entry, if context was requested.
+    pub after_context: Vec<String>,
+    /// The proposed replacement, if a replacement was requested.
+    pub edit: Option<Edit>,
 }
 
 pub struct SearchResult {
     pub search: SearchId,
     pub path: PathBuf,
+    /// Path of the searched file inside the archive at `path`.
+    pub inner_path: Option<PathBuf>,
     pub path_matches: Vec<Match>,
     pub entries: Vec<ResultEntry>,
+    /// Set for files that did not match an inverted search.
+    pub without_match: bool,
+    /// Why no edits were proposed for this file, despite a requested replacement.
+    pub replace_unavailable: Option<String>,
 }
 
 impl SearchResult {
     pub fn is_empty(&self) -> bool {
-        self.entries.is_empty() && self.path_matches.is_empty()
+        self.entries.is_empty() && self.path_matches.is_empty() && !self.without_match
+    }
+
+    /// Number of matches in the content and the file name.
+    pub fn match_count(&self) -> usize {
+        let content_matches: usize = self.entries.iter().map(|entry| entry.matches.len()).sum();
+        content_matches + self.path_matches.len()
     }
 }
 
 pub struct SearchError {
     pub search: SearchId,
+    pub kind: ErrorKind,
+    /// The file or directory which caused the error.
     pub path: PathBuf,
     pub message: String,
 }
 
+/// What went wrong, for grouping errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    PermissionDenied,
+    /// Any other failure to read a file or directory.
+    Io,
+    CorruptPdf,
+    PasswordProtected,
+    /// An office document which is not a valid zip archive of XML files.
+    InvalidOfficeArchive,
+    SymlinkLoop,
+    /// An external preprocessor command failed or timed out.
+    Preprocessor,
+    /// An invalid search pattern, file pattern, file type or encoding.
+    Pattern,
+    /// An archive inside of another archive, which is not searched.
+    NestedArchive,
+}
+
 pub enum SearchMessage {
     Result(SearchResult),
     Error(SearchError),
-    Completed { search: SearchId },
+    Progress(SearchProgress),
+    Completed {
+        search: SearchId,
+        stats: SearchStats,
+    },
 }
 
 impl SearchMessage {
@@ -46,6 +92,7 @@ impl SearchMessage {
         *match self {
             Self::Result(SearchResult { search, .. }) => search,
             Self::Error(SearchError { search, .. }) => search,
+            Self::Progress(SearchProgress { search, .. }) => search,
             Self::Completed { search, .. } => search,
         }
     }
@@ -1,30 +1,88 @@
 use crate::{
-    extra,
-    result::{Location, SearchError},
-    utils, ResultEntry, SearchEngine, SearchMessage, SearchResult,
+    engine::SearchContext,
+    extra::{
+        self, cache::TextCache, CancellableReader, ExtractedText, Extractor, ExtractorRegistry,
+        PageFn,
+    },
+    file_types::{self, FileType, FileTypeMatcher, FileTypes},
+    index::{self, SearchIndex},
+    matcher::{MatcherBuilder, SearchMatcher},
+    paths,
+    progress::ProgressTracker,
+    query::Query,
+    replace::Edit,
+    result::Location,
+    sort::SortOrder,
+    stats::{SkipClassifier, SkipReason},
+    utils, ErrorKind, ResultEntry, SearchMessage, SearchResult,
 };
+use gio::prelude::CancellableExt;
 use grep::{
     matcher::{Match, Matcher},
-    regex::{RegexMatcher, RegexMatcherBuilder},
-    searcher::SearcherBuilder,
+    searcher::{Encoding, Searcher, SearcherBuilder, SinkContextKind},
 };
 use ignore::{WalkBuilder, WalkState};
 use std::{
     error::Error,
-    io,
-    path::PathBuf,
-    sync::{
-        atomic::{AtomicUsize, Ordering},
-        Arc,
-    },
+    ffi::OsStr,
+    fs::{self, Metadata},
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
 };
 
 #[derive(Debug, Clone)]
 pub struct SearchParameters {
     pub base_directory: PathBuf,
-    pub content_pattern: String,
-    pub path_pattern: String,
+    pub terms: Vec<SearchTerm>,
+    /// Globs selecting the searched paths, prefixed with `!` to exclude paths instead.
+    /// Paths matching them are searched or skipped regardless of the file types.
+    pub path_patterns: Vec<String>,
+    /// Only files of these types are searched, unless there are none,
+    /// which applies to all paths which no path pattern matches.
+    pub file_types: Vec<FileType>,
+    pub before_context: usize,
+    pub after_context: usize,
+    /// Replacement for the matches, which may reference capture groups as `$1` or `$name`.
+    pub replacement: Option<String>,
+    /// Encoding of files without a byte order mark, UTF-8 if unset.
+    pub encoding: Option<String>,
+    pub filters: SearchFilters,
     pub flags: SearchFlags,
+    /// Extractors for custom formats, which take precedence over the built-in ones.
+    pub extractors: ExtractorRegistry,
+    /// Results are held back until the search is done, unless they are unsorted.
+    pub sort: SortOrder,
+    /// The file of an index covering the base directory, which is used to skip
+    /// documents and updated with the documents extracted by the search.
+    /// It is only loaded if the terms contain text every match has to contain.
+    pub index: Option<PathBuf>,
+    /// Keeps the text extracted from documents for later searches.
+    pub text_cache: Option<TextCache>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchTerm {
+    pub pattern: String,
+    /// Files in which an excluded term matches are not reported.
+    pub exclude: bool,
+}
+
+impl SearchTerm {
+    pub fn include(pattern: impl Into<String>) -> Self {
+        SearchTerm {
+            pattern: pattern.into(),
+            exclude: false,
+        }
+    }
+
+    pub fn exclude(pattern: impl Into<String>) -> Self {
+        SearchTerm {
+            pattern: pattern.into(),
+            exclude: true,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -32,11 +90,23 @@ pub struct SearchFlags {
     pub path_pattern_explicit: bool,
 
     pub case_sensitive: bool,
+    /// Case insensitive, unless the pattern contains uppercase characters.
+    pub smart_case: bool,
     pub fixed_string: bool,
+    pub whole_word: bool,
+    pub multiline: bool,
+    /// Use PCRE2 instead of Rust's regex engine.
+    pub pcre2: bool,
+    /// Report the files without any match instead of the matches.
+    pub invert: bool,
 
     pub search_names: bool,
     pub search_pdf: bool,
     pub search_office: bool,
+    /// Search the decompressed content of gzip, xz, bzip2 and zstd files.
+    pub search_compressed: bool,
+    /// Search the files inside of zip and tar archives.
+    pub search_archives: bool,
 
     pub search_hidden: bool,
     pub search_ignored: bool,
@@ -45,145 +115,510 @@ pub struct SearchFlags {
     pub follow_links: bool,
 }
 
-pub type SharedSearchId = Arc<AtomicUsize>;
-pub type SearchId = usize;
+/// Restricts which files are searched.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchFilters {
+    /// Smallest file size in bytes.
+    pub min_size: Option<u64>,
+    /// Largest file size in bytes.
+    pub max_size: Option<u64>,
+    pub modified_after: Option<SystemTime>,
+    pub modified_before: Option<SystemTime>,
+    /// How many directories deep to descend, where 1 only
+    /// searches the files directly inside the base directory.
+    pub max_depth: Option<usize>,
+}
 
-/// Blocking content search
-pub fn run(engine: SearchEngine, params: SearchParameters) {
-    let search = engine.current_search_id.load(Ordering::Acquire);
+impl SearchFilters {
+    /// Whether a file with the given metadata passes the size and time filters.
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min) {
+            return false;
+        }
+        if self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
 
-    let matcher = RegexMatcherBuilder::new()
-        .case_insensitive(!params.flags.case_sensitive)
-        .fixed_strings(params.flags.fixed_string)
-        .build(&params.content_pattern);
+        if self.modified_after.is_some() || self.modified_before.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.modified_after.is_some_and(|after| modified < after) {
+                return false;
+            }
+            if self
+                .modified_before
+                .is_some_and(|before| modified >= before)
+            {
+                return false;
+            }
+        }
 
-    if let Err(err) = matcher {
-        _ = engine.sender.send(SearchMessage::Error(SearchError {
-            search,
-            path: params.base_directory,
-            message: format!("Failed to start search: {err}"),
-        }));
-        _ = engine.sender.send(SearchMessage::Completed { search });
-        return;
+        true
     }
-    let matcher = matcher.unwrap();
+
+    pub(crate) fn needs_metadata(&self) -> bool {
+        self.min_size.is_some() || self.modified_after.is_some() || self.modified_before.is_some()
+    }
+}
+
+pub type SearchId = usize;
+
+/// Blocking content search
+pub(crate) fn run(context: SearchContext, params: SearchParameters) {
+    let search = context.id();
+
+    let builder = MatcherBuilder::new(&params.flags);
+    let query = match Query::new(&builder, &params.terms) {
+        Ok(query) => query,
+        Err(err) => {
+            context.abort(
+                params.base_directory,
+                format!("Failed to start search: {err}"),
+            );
+            return;
+        }
+    };
 
     let threads = match std::thread::available_parallelism() {
         Ok(cores) => cores.get(),
         Err(_) => 2,
     };
 
-    let pattern = if !params.path_pattern.is_empty() {
-        // Validity of path patterns should be checked externally.
-        glob::Pattern::new(&params.path_pattern).ok()
+    let builtin_types = FileTypes::builtin();
+    let archive_type = builtin_types
+        .get(file_types::ARCHIVES)
+        .expect("built-in types are valid");
+
+    // Archives have to be walked even if only their entries match the patterns.
+    let mut path_patterns = params.path_patterns.clone();
+    if params.flags.search_archives && path_patterns.iter().any(|p| !p.starts_with('!')) {
+        let globs = archive_type.globs.iter().map(|glob| format!("**/{glob}"));
+        path_patterns.splice(0..0, globs);
+    }
+
+    let overrides = match paths::path_overrides(
+        &params.base_directory,
+        &path_patterns,
+        params.flags.path_pattern_explicit,
+    ) {
+        Ok(overrides) => overrides,
+        Err(err) => {
+            context.abort(
+                params.base_directory,
+                format!("Invalid file pattern: {err}"),
+            );
+            return;
+        }
+    };
+
+    let types = match FileTypeMatcher::new(&params.file_types) {
+        Ok(types) => types,
+        Err(err) => {
+            context.abort(params.base_directory, format!("Invalid file type: {err}"));
+            return;
+        }
+    };
+
+    let encoding = match params.encoding.as_deref().map(Encoding::new).transpose() {
+        Ok(encoding) => encoding,
+        Err(err) => {
+            context.abort(params.base_directory, format!("Invalid encoding: {err}"));
+            return;
+        }
+    };
+
+    // The same goes for the selected file types.
+    let walked_types = if params.flags.search_archives && !params.file_types.is_empty() {
+        let mut walked = params.file_types.clone();
+        walked.push(archive_type.clone());
+        FileTypeMatcher::new(&walked).expect("selected types are valid")
     } else {
-        None
+        types.clone()
     };
 
-    let walker = WalkBuilder::new(&params.base_directory)
+    // Edits would refer to offsets in the transcoded contents.
+    let transcoded = encoding.is_some();
+
+    let filters = params.filters;
+    let mut walk_builder = WalkBuilder::new(&params.base_directory);
+    walk_builder
         .git_ignore(!params.flags.search_ignored)
         .ignore(!params.flags.search_ignored)
         .hidden(!params.flags.search_hidden)
         .follow_links(params.flags.follow_links)
         .same_file_system(params.flags.same_filesystem)
+        .max_depth(filters.max_depth)
+        .max_filesize(filters.max_size)
+        .overrides(overrides.clone())
+        .types(walked_types.clone().into_types())
         .threads(threads)
-        .filter_entry(move |dir| match (dir.path().is_file(), pattern.as_ref()) {
-            (true, Some(pattern)) => {
-                let relative_dir = dir.path().strip_prefix(&params.base_directory).unwrap();
-                pattern.matches_path_with(
-                    relative_dir,
-                    glob::MatchOptions {
-                        case_sensitive: false,
-                        require_literal_separator: params.flags.path_pattern_explicit,
-                        require_literal_leading_dot: false,
-                    },
-                )
-            }
-            _ => true,
-        })
-        .build_parallel();
+        .filter_entry(move |entry| {
+            !filters.needs_metadata()
+                || !entry.path().is_file()
+                || entry.metadata().is_ok_and(|m| filters.matches(&m))
+        });
+    let walker = walk_builder.build_parallel();
 
-    let mut preprocessors: Vec<(_, extra::ExtraFn)> = Vec::new();
-    if params.flags.search_pdf {
-        preprocessors.push((extra::pdf::EXTENSIONS, extra::pdf::process));
-    }
-    if params.flags.search_office {
-        preprocessors.push((extra::office::EXTENSIONS, extra::office::process));
-    }
+    let progress = Arc::new(ProgressTracker::new(context.clone()));
+    let classifier = Arc::new(SkipClassifier {
+        overrides: overrides.clone(),
+        types: walked_types,
+        filters,
+        skip_hidden: !params.flags.search_hidden,
+        follow_links: params.flags.follow_links,
+    });
 
+    // Registered extractors take precedence over the built-in ones.
+    let mut extractors = params.extractors.clone();
+    extractors.add_builtin(params.flags.search_pdf, params.flags.search_office);
+    let preprocessors = Preprocessors {
+        extractors,
+        compressed: params.flags.search_compressed.then(|| {
+            builtin_types
+                .matcher(&[file_types::COMPRESSED])
+                .expect("built-in types are valid")
+        }),
+    };
+    let archives = params.flags.search_archives.then(|| {
+        builtin_types
+            .matcher(&[file_types::ARCHIVES])
+            .expect("built-in types are valid")
+    });
+
+    // Transcoding could produce text which is not in the index.
+    let index = params
+        .index
+        .clone()
+        .filter(|_| !transcoded)
+        .and_then(|file| {
+            let required = index::required_trigrams(&params.terms, &params.flags);
+            (!required.is_empty()).then(|| Arc::new(SearchIndex::open(file, required)))
+        });
+
+    let results = ResultSender::new(context.clone(), params.sort);
     walker.run(|| {
-        let engine = engine.clone();
-        let matcher = matcher.clone();
+        let context = context.clone();
+        let results = results.clone();
+        let query = query.clone();
+        let matcher = query.matcher.clone();
         let preprocessors = preprocessors.clone();
-        let mut sink = SearchSink::new(matcher.clone());
+        let index = index.clone();
+        let text_cache = params.text_cache.clone();
+        let progress = progress.clone();
+        let classifier = classifier.clone();
+        let archives = archives.clone();
+        let overrides = overrides.clone();
+        let types = types.clone();
+        let replacement = params.replacement.clone();
+        let base_directory = params.base_directory.clone();
+        let mut sink = SearchSink::new(matcher.clone(), context.cancellable().clone());
         let mut searcher = SearcherBuilder::new()
             .binary_detection(grep::searcher::BinaryDetection::quit(b'\x01'))
             .line_number(true)
+            .multi_line(params.flags.multiline)
+            .encoding(encoding.clone())
+            .before_context(params.before_context)
+            .after_context(params.after_context)
             .build();
 
         Box::new(move |entry: Result<ignore::DirEntry, ignore::Error>| {
-            if engine.current_search_id.load(Ordering::Relaxed) != search {
+            if context.is_cancelled() {
                 return WalkState::Quit;
             }
 
-            if entry.is_err() {
-                return WalkState::Continue;
-            }
-            let entry = entry.unwrap();
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    let (kind, path) = walk_error(&err);
+                    let path = path.unwrap_or(&base_directory).to_path_buf();
+                    context.send_error(path, kind, err.to_string());
+                    return WalkState::Continue;
+                }
+            };
             let file_type = entry.file_type().unwrap();
 
+            // The walker does not tell why it skips files, so the files
+            // of each directory it enters are classified on their own.
+            if file_type.is_dir() && filters.max_depth.is_none_or(|max| entry.depth() < max) {
+                classifier.classify_dir(&base_directory, entry.path(), |reason| match reason {
+                    Some(reason) => progress.skipped(reason),
+                    None => progress.not_ignored(),
+                });
+            }
+
             if file_type.is_symlink() {
                 return WalkState::Continue;
             }
 
             let mut path_matches = Vec::new();
-            if params.flags.search_names {
-                let file_name = entry.file_name();
-                matcher
-                    .find_iter(file_name.as_encoded_bytes(), |m| {
-                        path_matches.push(m);
-                        true
-                    })
-                    .expect("RegexMatcher should never throw an error");
+            if params.flags.search_names && !params.flags.invert {
+                path_matches = name_matches(&query, entry.file_name());
             }
 
             if !file_type.is_file() {
                 return WalkState::Continue;
             }
+            progress.discovered();
 
-            let extension = entry
-                .path()
-                .extension()
-                .unwrap_or_default()
-                .to_string_lossy();
+            let size = entry.metadata().map(|m| m.len()).unwrap_or_default();
+
+            if archives
+                .as_ref()
+                .is_some_and(|archives| archives.is_match(entry.path()))
+            {
+                let _searching = progress.searching(entry.path(), size);
+                let archive_result =
+                    extra::archive::for_each_entry(entry.path(), |inner_path, reader| {
+                        if context.is_cancelled() {
+                            return false;
+                        }
+
+                        // The same precedence as for the walked files.
+                        let glob = overrides.matched(inner_path, false);
+                        let allowed =
+                            glob.is_whitelist() || (!glob.is_ignore() && types.allows(inner_path));
+                        if !allowed {
+                            return true;
+                        }
+
+                        let mut path_matches = Vec::new();
+                        if params.flags.search_names && !params.flags.invert {
+                            path_matches =
+                                name_matches(&query, inner_path.file_name().unwrap_or_default());
+                        }
+
+                        // Archives are only searched one level deep.
+                        if archives
+                            .as_ref()
+                            .is_some_and(|archives| archives.is_match(inner_path))
+                        {
+                            context.send_error(
+                                extra::archive::entry_path(entry.path(), inner_path),
+                                ErrorKind::NestedArchive,
+                                "archives inside of archives are not searched".to_string(),
+                            );
+                            return true;
+                        }
 
-            let pre_processor = preprocessors
-                .iter()
-                .find(|(exts, _)| exts.contains(&extension.as_ref()))
-                .map(|(_, extract_fn)| extract_fn);
+                        let pre_processor = preprocessors.find(inner_path);
+
+                        sink.replacement = None;
+                        let search_result = match pre_processor {
+                            Some(pre_processor) => extra::archive::with_temp_file(
+                                inner_path,
+                                reader,
+                                context.cancellable(),
+                                |path| {
+                                    pre_processor.search(
+                                        &mut searcher,
+                                        &matcher,
+                                        path,
+                                        &mut sink,
+                                        context.cancellable(),
+                                        TextStorage::default(),
+                                    )
+                                },
+                            ),
+                            None => searcher.search_reader(
+                                &matcher,
+                                CancellableReader::new(reader, context.cancellable()),
+                                &mut sink,
+                            ),
+                        };
+
+                        if context.is_cancelled() {
+                            return false;
+                        }
+
+                        if let Err(err) = search_result {
+                            sink.take_entries();
+                            let fallback =
+                                pre_processor.map_or(ErrorKind::Io, Preprocessor::error_kind);
+                            context.send_error(
+                                extra::archive::entry_path(entry.path(), inner_path),
+                                error_kind(&*err, fallback),
+                                format!("failed to search file: {err}"),
+                            );
+                            return true;
+                        }
+
+                        let is_binary = sink.is_binary();
+                        let (entries, without_match) = reported_entries(
+                            &query,
+                            params.flags.invert,
+                            sink.take_entries(),
+                            is_binary,
+                        );
+                        progress.found_matches(count_matches(&entries));
+
+                        let result = SearchResult {
+                            search,
+                            path: entry.path().to_path_buf(),
+                            inner_path: Some(inner_path.to_path_buf()),
+                            path_matches,
+                            entries,
+                            without_match,
+                            replace_unavailable: replacement
+                                .as_ref()
+                                .map(|_| "Replacing text in archives is not supported".to_string()),
+                        };
+
+                        if result.is_empty() {
+                            return true;
+                        }
+
+                        results.send(result) && !context.is_cancelled()
+                    });
+
+                if context.is_cancelled() {
+                    return WalkState::Quit;
+                }
+
+                if let Err(err) = archive_result {
+                    context.send_error(
+                        entry.path().to_path_buf(),
+                        error_kind(&*err, ErrorKind::Io),
+                        format!("failed to search archive: {err}"),
+                    );
+                }
+
+                let result = SearchResult {
+                    search,
+                    path: entry.path().to_path_buf(),
+                    inner_path: None,
+                    path_matches,
+                    entries: Vec::new(),
+                    without_match: false,
+                    replace_unavailable: None,
+                };
+
+                if !results.send(result) {
+                    return WalkState::Quit;
+                }
+
+                return WalkState::Continue;
+            }
+
+            let pre_processor = preprocessors.detect(entry.path());
+
+            // Documents which the index rules out are not extracted again.
+            if let (Some(index), Some(Preprocessor::Extractor(extractor))) = (&index, pre_processor)
+            {
+                let ruled_out = extractor.is_cacheable()
+                    && entry
+                        .metadata()
+                        .is_ok_and(|metadata| index.rules_out(entry.path(), &metadata));
+                if ruled_out {
+                    progress.skipped(SkipReason::RuledOut);
+
+                    let result = SearchResult {
+                        search,
+                        path: entry.path().to_path_buf(),
+                        inner_path: None,
+                        path_matches,
+                        entries: Vec::new(),
+                        without_match: false,
+                        replace_unavailable: None,
+                    };
+
+                    if !result.is_empty() && !results.send(result) {
+                        return WalkState::Quit;
+                    }
+                    return WalkState::Continue;
+                }
+            }
+
+            let _searching = progress.searching(entry.path(), size);
+
+            // Edits are only meaningful for files which are searched as they are.
+            sink.replacement = match pre_processor {
+                None => replacement.clone(),
+                Some(_) => None,
+            };
 
             let search_result = match pre_processor {
-                Some(process) => process(&mut searcher, &matcher, entry.path(), &mut sink),
+                Some(pre_processor) => pre_processor.search(
+                    &mut searcher,
+                    &matcher,
+                    entry.path(),
+                    &mut sink,
+                    context.cancellable(),
+                    TextStorage {
+                        index: index.as_deref(),
+                        cache: text_cache.as_ref(),
+                    },
+                ),
                 None => searcher.search_path(&matcher, entry.path(), &mut sink),
             };
 
+            // Whatever was found before the search got cancelled is incomplete.
+            if context.is_cancelled() {
+                return WalkState::Quit;
+            }
+
             if let Err(err) = search_result {
-                _ = engine.send_error(
-                    search,
+                let fallback = pre_processor.map_or(ErrorKind::Io, Preprocessor::error_kind);
+                context.send_error(
                     entry.path().to_path_buf(),
+                    error_kind(&*err, fallback),
                     format!("failed to search file: {err}"),
                 );
                 return WalkState::Continue;
             }
 
+            let is_binary = sink.is_binary();
+            let (mut entries, without_match) =
+                reported_entries(&query, params.flags.invert, sink.take_entries(), is_binary);
+            progress.found_matches(count_matches(&entries));
+            if is_binary {
+                progress.skipped(SkipReason::Binary);
+            }
+
+            let mut replace_unavailable = None;
+            if replacement.is_some() {
+                if let Some(pre_processor) = pre_processor {
+                    replace_unavailable = Some(format!(
+                        "Replacing text in {} files is not supported",
+                        pre_processor.format(entry.path())
+                    ));
+                } else if transcoded {
+                    entries.iter_mut().for_each(|entry| entry.edit = None);
+                    replace_unavailable = Some(
+                        "Replacing text is not supported with a fallback encoding".to_string(),
+                    );
+                } else if is_binary {
+                    entries.iter_mut().for_each(|entry| entry.edit = None);
+                    replace_unavailable =
+                        Some("Replacing text in binary files is not supported".to_string());
+                } else if let Some((bom_encoding, bom_length)) = byte_order_mark(entry.path()) {
+                    // The searcher strips the byte order mark and decodes anything but UTF-8.
+                    if bom_encoding == encoding_rs::UTF_8 {
+                        for edit in entries.iter_mut().filter_map(|entry| entry.edit.as_mut()) {
+                            edit.offset += bom_length as u64;
+                        }
+                    } else {
+                        entries.iter_mut().for_each(|entry| entry.edit = None);
+                        replace_unavailable = Some(format!(
+                            "Replacing text in {} files is not supported",
+                            bom_encoding.name()
+                        ));
+                    }
+                }
+            }
+
             let result = SearchResult {
                 search,
                 path: entry.path().to_path_buf(),
+                inner_path: None,
                 path_matches,
-                entries: sink.take_entries(),
+                entries,
+                without_match,
+                replace_unavailable,
             };
 
-            if engine.sender.send(SearchMessage::Result(result)).is_err() {
+            if !results.send(result) {
                 return WalkState::Quit;
             }
 
@@ -191,29 +626,330 @@ pub fn run(engine: SearchEngine, params: SearchParameters) {
         })
     });
 
-    _ = engine.sender.send(SearchMessage::Completed { search });
+    // Only once the walk is done is it known how many files there are.
+    if !context.is_cancelled() {
+        progress.discovery_completed();
+    }
+    results.finish(params.sort);
+    progress.report(true);
+
+    context.complete(progress.stats());
+
+    // Saving a large index should not hold back the completion.
+    if let Some(index) = index {
+        index.save();
+    }
+}
+
+/// Selects how files are turned into searchable text.
+#[derive(Clone)]
+struct Preprocessors {
+    extractors: ExtractorRegistry,
+    /// Compressed files are decompressed while they are searched.
+    compressed: Option<FileTypeMatcher>,
+}
+
+impl Preprocessors {
+    /// The preprocessor for a file by its name,
+    /// which is all that is known about entries of archives.
+    fn find(&self, path: &Path) -> Option<Preprocessor<'_>> {
+        if let Some(extractor) = self.extractors.find(path) {
+            return Some(Preprocessor::Extractor(extractor.as_ref()));
+        }
+
+        self.compressed
+            .as_ref()
+            .filter(|types| types.is_match(path))
+            .map(|_| Preprocessor::Decompress)
+    }
+
+    /// The preprocessor for a file by its name, or otherwise by its content.
+    fn detect(&self, path: &Path) -> Option<Preprocessor<'_>> {
+        self.find(path).or_else(|| {
+            self.extractors
+                .detect(path)
+                .map(|extractor| Preprocessor::Extractor(extractor.as_ref()))
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Preprocessor<'a> {
+    Extractor(&'a dyn Extractor),
+    Decompress,
+}
+
+impl Preprocessor<'_> {
+    fn search(
+        self,
+        searcher: &mut Searcher,
+        matcher: &SearchMatcher,
+        path: &Path,
+        sink: &mut SearchSink,
+        cancellable: &gio::Cancellable,
+        storage: TextStorage,
+    ) -> Result<(), Box<dyn Error>> {
+        match self {
+            Preprocessor::Extractor(extractor) => {
+                storage.extract_pages(extractor, path, cancellable, &mut |page, text| {
+                    extra::search_page(searcher, matcher, sink, page, text)
+                })
+            }
+            Preprocessor::Decompress => {
+                extra::compressed::process(searcher, matcher, path, sink, cancellable)
+            }
+        }
+    }
+
+    /// The name of the format of a file, like `PDF`.
+    fn format(self, path: &Path) -> String {
+        match self {
+            Preprocessor::Extractor(extractor) => extractor.file_type().name,
+            Preprocessor::Decompress => path
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_uppercase(),
+        }
+    }
+
+    /// The kind of errors which are not recognized otherwise.
+    fn error_kind(self) -> ErrorKind {
+        match self {
+            Preprocessor::Extractor(extractor) => extractor.error_kind(),
+            Preprocessor::Decompress => ErrorKind::Io,
+        }
+    }
+}
+
+/// Where the text extracted from documents is kept for later searches,
+/// which is not done for temporary files.
+#[derive(Clone, Copy, Default)]
+struct TextStorage<'a> {
+    index: Option<&'a SearchIndex>,
+    cache: Option<&'a TextCache>,
+}
+
+impl TextStorage<'_> {
+    /// Extracts the text page by page, unless it is cached.
+    fn extract_pages(
+        self,
+        extractor: &dyn Extractor,
+        path: &Path,
+        cancellable: &gio::Cancellable,
+        f: &mut PageFn,
+    ) -> Result<(), Box<dyn Error>> {
+        // Command output can change without the file changing, so it is not kept.
+        let index = self.index.filter(|_| extractor.is_cacheable());
+        let cache = self.cache.filter(|_| extractor.is_cacheable());
+
+        // Taken first, so that changes during the extraction are noticed later.
+        let metadata = match (index, cache) {
+            (None, None) => None,
+            _ => fs::metadata(path).ok(),
+        };
+        let Some(metadata) = metadata else {
+            return extractor.extract_pages(path, cancellable, f);
+        };
+
+        let name = extractor.file_type().name;
+        let text = match cache.and_then(|cache| cache.get(&name, path, &metadata)) {
+            Some(text) => {
+                text.for_each_page(f)?;
+                text
+            }
+            None => {
+                // The pages are still handed on right away,
+                // but kept until the whole text can be stored.
+                let (mut pages, mut paged) = (Vec::new(), false);
+                extractor.extract_pages(path, cancellable, &mut |page, text| {
+                    paged |= page.is_some();
+                    pages.push(text.to_string());
+                    f(page, text)
+                })?;
+
+                let text = if paged {
+                    ExtractedText::Pages(pages)
+                } else {
+                    ExtractedText::Text(pages.concat())
+                };
+                if let Some(cache) = cache {
+                    cache.insert(&name, path, &metadata, &text);
+                }
+                text
+            }
+        };
+
+        if let Some(index) = index {
+            index.insert(path, &metadata, &text);
+        }
+        Ok(())
+    }
+}
+
+/// Sends results as soon as they are found, or collects them to send them sorted.
+#[derive(Clone)]
+struct ResultSender {
+    context: SearchContext,
+    collected: Option<Arc<Mutex<Vec<SearchResult>>>>,
+}
+
+impl ResultSender {
+    fn new(context: SearchContext, sort: SortOrder) -> Self {
+        ResultSender {
+            context,
+            collected: (sort != SortOrder::Unsorted).then(Default::default),
+        }
+    }
+
+    /// Returns whether the search should go on.
+    fn send(&self, result: SearchResult) -> bool {
+        let Some(collected) = &self.collected else {
+            return self.context.send(SearchMessage::Result(result));
+        };
+
+        if !result.is_empty() {
+            collected.lock().unwrap().push(result);
+        }
+        !self.context.is_cancelled()
+    }
+
+    /// Sends the collected results, once all files have been searched.
+    fn finish(&self, sort: SortOrder) {
+        let Some(collected) = &self.collected else {
+            return;
+        };
+
+        let mut results = std::mem::take(&mut *collected.lock().unwrap());
+        sort.sort(&mut results);
+        for result in results {
+            if self.context.is_cancelled() || !self.context.send(SearchMessage::Result(result)) {
+                break;
+            }
+        }
+    }
+}
+
+/// Classifies an error from searching a file, if it is recognized.
+fn error_kind(err: &(dyn Error + 'static), fallback: ErrorKind) -> ErrorKind {
+    if let Some(err) = err.downcast_ref::<io::Error>() {
+        return io_error_kind(err);
+    }
+    if let Some(err) = err.downcast_ref::<gio::glib::Error>() {
+        if err.matches(poppler::Error::Encrypted) {
+            return ErrorKind::PasswordProtected;
+        }
+    }
+    fallback
+}
+
+fn io_error_kind(err: &io::Error) -> ErrorKind {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Io,
+    }
+}
+
+/// Classifies an error from walking the directories, along with the path it is about.
+fn walk_error(err: &ignore::Error) -> (ErrorKind, Option<&Path>) {
+    match err {
+        ignore::Error::WithPath { path, err } => {
+            let (kind, inner_path) = walk_error(err);
+            (kind, inner_path.or(Some(path)))
+        }
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error(err)
+        }
+        ignore::Error::Partial(errs) => errs.first().map_or((ErrorKind::Io, None), walk_error),
+        ignore::Error::Loop { child, .. } => (ErrorKind::SymlinkLoop, Some(child)),
+        ignore::Error::Io(err) => (io_error_kind(err), None),
+        ignore::Error::Glob { .. }
+        | ignore::Error::UnrecognizedFileType(_)
+        | ignore::Error::InvalidDefinition => (ErrorKind::Pattern, None),
+    }
+}
+
+/// The encoding and length of the byte order mark a file starts with, if any.
+fn byte_order_mark(path: &Path) -> Option<(&'static encoding_rs::Encoding, usize)> {
+    let mut start = Vec::with_capacity(3);
+    fs::File::open(path)
+        .and_then(|file| file.take(3).read_to_end(&mut start))
+        .ok()?;
+    encoding_rs::Encoding::for_bom(&start)
+}
+
+fn name_matches(query: &Query, file_name: &OsStr) -> Vec<Match> {
+    let mut matches = Vec::new();
+    query
+        .include_matcher
+        .find_iter(file_name.as_encoded_bytes(), |m| {
+            matches.push(m);
+            true
+        })
+        .unwrap_or_else(|err| log::debug!("Failed to match file name: {err}"));
+    matches
+}
+
+/// Decides which entries of a searched file are reported,
+/// and whether the file is reported as without match.
+fn reported_entries(
+    query: &Query,
+    invert: bool,
+    entries: Vec<ResultEntry>,
+    is_binary: bool,
+) -> (Vec<ResultEntry>, bool) {
+    let is_satisfied = query.is_satisfied_by(&entries);
+
+    if invert {
+        (Vec::new(), !is_satisfied && !is_binary)
+    } else if is_satisfied {
+        (entries, false)
+    } else {
+        (Vec::new(), false)
+    }
+}
+
+fn count_matches(entries: &[ResultEntry]) -> u64 {
+    entries.iter().map(|entry| entry.matches.len() as u64).sum()
 }
 
 pub struct SearchSink {
     pub page: Option<u64>,
-    matcher: RegexMatcher,
+    /// Proposes an edit for each entry, if set.
+    pub replacement: Option<String>,
+    matcher: SearchMatcher,
     entries: Vec<ResultEntry>,
+    before_context: Vec<String>,
+    binary: bool,
+    /// Stops the searcher at the next line once cancelled.
+    cancellable: gio::Cancellable,
 }
 
 impl SearchSink {
-    pub fn new(matcher: RegexMatcher) -> Self {
+    pub fn new(matcher: SearchMatcher, cancellable: gio::Cancellable) -> Self {
         SearchSink {
             page: None,
+            replacement: None,
             matcher,
             entries: Vec::new(),
+            before_context: Vec::new(),
+            binary: false,
+            cancellable,
         }
     }
 
     pub fn take_entries(&mut self) -> Vec<ResultEntry> {
         self.page = None;
+        self.binary = false;
+        self.before_context.clear();
         std::mem::take(&mut self.entries)
     }
 
+    /// Whether binary data was encountered since the last call to `take_entries`.
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
     pub fn extract_matches(
         &self,
         searcher: &grep::searcher::Searcher,
@@ -240,12 +976,37 @@ impl grep::searcher::Sink for SearchSink {
         searcher: &grep::searcher::Searcher,
         mat: &grep::searcher::SinkMatch<'_>,
     ) -> Result<bool, Self::Error> {
+        if self.cancellable.is_cancelled() {
+            return Ok(false);
+        }
+
         let matches = self.extract_matches(searcher, mat.buffer(), mat.bytes_range_in_buffer())?;
         let content = String::from_utf8_lossy(mat.bytes())
             .trim_ascii_end()
             .to_string();
 
+        let edit = match &self.replacement {
+            Some(replacement) => {
+                let mut replaced = Vec::new();
+                utils::replace_in_context(
+                    searcher,
+                    &self.matcher,
+                    mat.buffer(),
+                    mat.bytes_range_in_buffer(),
+                    replacement.as_bytes(),
+                    &mut replaced,
+                )?;
+                Some(Edit {
+                    offset: mat.absolute_byte_offset(),
+                    original: mat.bytes().to_vec(),
+                    replacement: replaced,
+                })
+            }
+            None => None,
+        };
+
         let line = mat.line_number().unwrap();
+        let end_line = line + mat.lines().count().saturating_sub(1) as u64;
         let location = match self.page {
             None => Location::Text { line },
             Some(page) => Location::Document { page, line },
@@ -253,10 +1014,294 @@ impl grep::searcher::Sink for SearchSink {
 
         self.entries.push(ResultEntry {
             location,
+            end_line,
             content,
             matches,
+            before_context: std::mem::take(&mut self.before_context),
+            after_context: Vec::new(),
+            edit,
         });
 
         Ok(true)
     }
+
+    fn context(
+        &mut self,
+        _searcher: &grep::searcher::Searcher,
+        context: &grep::searcher::SinkContext<'_>,
+    ) -> Result<bool, Self::Error> {
+        if self.cancellable.is_cancelled() {
+            return Ok(false);
+        }
+
+        let content = String::from_utf8_lossy(context.bytes())
+            .trim_ascii_end()
+            .to_string();
+
+        match context.kind() {
+            SinkContextKind::Before => self.before_context.push(content),
+            SinkContextKind::After => {
+                if let Some(entry) = self.entries.last_mut() {
+                    entry.after_context.push(content);
+                }
+            }
+            SinkContextKind::Other => {}
+        }
+
+        Ok(true)
+    }
+
+    fn binary_data(
+        &mut self,
+        _searcher: &grep::searcher::Searcher,
+        _binary_byte_offset: u64,
+    ) -> Result<bool, Self::Error> {
+        self.binary = true;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{walk_error, SearchSink};
+    use crate::{
+        file_types::FileTypes,
+        matcher::MatcherBuilder,
+        replace::apply_edits,
+        testing::{search_parameters, TempDir},
+        ErrorKind, Location, SearchEngine, SearchFlags, SearchMessage, SearchParameters,
+    };
+    use gio::prelude::CancellableExt;
+    use grep::searcher::SearcherBuilder;
+    use std::{
+        fs, io,
+        path::{Path, PathBuf},
+    };
+
+    #[test]
+    fn test_multiline_entry() {
+        let flags = SearchFlags {
+            multiline: true,
+            ..Default::default()
+        };
+        let matcher = MatcherBuilder::new(&flags).build(r"foo\nbar").unwrap();
+        let mut searcher = SearcherBuilder::new()
+            .line_number(true)
+            .multi_line(true)
+            .build();
+        let mut sink = SearchSink::new(matcher.clone(), gio::Cancellable::new());
+
+        searcher
+            .search_slice(&matcher, b"one\nfoo\nbar\ntwo\n", &mut sink)
+            .unwrap();
+
+        let entries = sink.take_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].location, Location::Text { line: 2 });
+        assert_eq!(entries[0].end_line, 3);
+        assert_eq!(entries[0].content, "foo\nbar");
+        assert_eq!(entries[0].matches.len(), 1);
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let matcher = MatcherBuilder::new(&SearchFlags::default())
+            .build("match")
+            .unwrap();
+        let mut searcher = SearcherBuilder::new()
+            .line_number(true)
+            .before_context(1)
+            .after_context(2)
+            .build();
+        let mut sink = SearchSink::new(matcher.clone(), gio::Cancellable::new());
+
+        searcher
+            .search_slice(&matcher, b"a\nb\nmatch\nc\nd\ne\n", &mut sink)
+            .unwrap();
+
+        let entries = sink.take_entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].before_context, ["b"]);
+        assert_eq!(entries[0].after_context, ["c", "d"]);
+    }
+
+    #[test]
+    fn test_walk_error_kind() {
+        let err = ignore::Error::WithDepth {
+            depth: 2,
+            err: Box::new(ignore::Error::WithPath {
+                path: PathBuf::from("/a/b"),
+                err: Box::new(ignore::Error::Io(io::ErrorKind::PermissionDenied.into())),
+            }),
+        };
+        assert_eq!(
+            walk_error(&err),
+            (ErrorKind::PermissionDenied, Some(Path::new("/a/b")))
+        );
+
+        let err = ignore::Error::Loop {
+            ancestor: PathBuf::from("/a"),
+            child: PathBuf::from("/a/b/link"),
+        };
+        assert_eq!(
+            walk_error(&err),
+            (ErrorKind::SymlinkLoop, Some(Path::new("/a/b/link")))
+        );
+    }
+
+    #[test]
+    fn test_cancelled_sink() {
+        let matcher = MatcherBuilder::new(&SearchFlags::default())
+            .build("match")
+            .unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let cancellable = gio::Cancellable::new();
+        let mut sink = SearchSink::new(matcher.clone(), cancellable.clone());
+
+        cancellable.cancel();
+        searcher
+            .search_slice(&matcher, b"match\nmatch\n", &mut sink)
+            .unwrap();
+
+        assert!(sink.take_entries().is_empty());
+    }
+
+    #[test]
+    fn test_replacement_edits() {
+        let matcher = MatcherBuilder::new(&SearchFlags::default())
+            .build(r"(\w+)@example")
+            .unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut sink = SearchSink::new(matcher.clone(), gio::Cancellable::new());
+        sink.replacement = Some("$1@example.org".to_string());
+
+        searcher
+            .search_slice(
+                &matcher,
+                b"none\nmail bob@example or amy@example\n",
+                &mut sink,
+            )
+            .unwrap();
+
+        let entries = sink.take_entries();
+        let edit = entries[0].edit.as_ref().unwrap();
+        assert_eq!(edit.offset, 5);
+        assert_eq!(edit.original, b"mail bob@example or amy@example\n");
+        assert_eq!(
+            edit.replacement,
+            b"mail bob@example.org or amy@example.org\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_after_byte_order_mark() {
+        let dir = TempDir::new("bom");
+        fs::write(dir.join("utf8.txt"), b"\xEF\xBB\xBFfoo\nbar foo\n").unwrap();
+        fs::write(dir.join("utf16.txt"), b"\xFF\xFEf\0o\0o\0\n\0").unwrap();
+
+        let engine = SearchEngine::default();
+        let handle = engine.search(SearchParameters {
+            replacement: Some("baz".to_string()),
+            ..search_parameters(dir.path(), "foo")
+        });
+
+        let mut results = Vec::new();
+        for message in handle.receiver().iter() {
+            match message {
+                SearchMessage::Result(result) => results.push(result),
+                SearchMessage::Completed { .. } => break,
+                _ => {}
+            }
+        }
+        let result = |name: &str| results.iter().find(|r| r.path.ends_with(name)).unwrap();
+
+        let utf8 = result("utf8.txt");
+        assert_eq!(utf8.replace_unavailable, None);
+        let edits = utf8
+            .entries
+            .iter()
+            .filter_map(|entry| entry.edit.clone())
+            .collect::<Vec<_>>();
+        let content = fs::read(dir.join("utf8.txt")).unwrap();
+        assert_eq!(
+            apply_edits(&content, &edits).unwrap(),
+            b"\xEF\xBB\xBFbaz\nbar baz\n"
+        );
+
+        // Edits of transcoded files would not fit the file on disk.
+        let utf16 = result("utf16.txt");
+        assert_eq!(utf16.entries.len(), 1);
+        assert!(utf16.entries[0].edit.is_none());
+        assert!(utf16.replace_unavailable.is_some());
+    }
+
+    #[test]
+    fn test_nested_archive() {
+        let tar = |files: &[(&str, &[u8])]| {
+            let mut builder = tar::Builder::new(Vec::new());
+            for (name, content) in files {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(content.len() as u64);
+                header.set_cksum();
+                builder.append_data(&mut header, name, *content).unwrap();
+            }
+            builder.into_inner().unwrap()
+        };
+
+        let dir = TempDir::new("nested-archive");
+        let inner = tar(&[("inner.txt", b"foo\n")]);
+        let outer = tar(&[("inner.tar", &inner), ("outer.txt", b"foo\n")]);
+        fs::write(dir.join("outer.tar"), outer).unwrap();
+
+        let mut params = search_parameters(dir.path(), "foo");
+        params.flags.search_archives = true;
+        let handle = SearchEngine::default().search(params);
+
+        let (mut found, mut errors) = (Vec::new(), Vec::new());
+        for message in handle.receiver().iter() {
+            match message {
+                SearchMessage::Result(result) if !result.entries.is_empty() => {
+                    found.push(result.inner_path.unwrap())
+                }
+                SearchMessage::Error(error) => errors.push((error.kind, error.path)),
+                SearchMessage::Completed { .. } => break,
+                _ => {}
+            }
+        }
+
+        assert_eq!(found, [Path::new("outer.txt")]);
+        assert_eq!(
+            errors,
+            [(ErrorKind::NestedArchive, dir.join("outer.tar!/inner.tar"))]
+        );
+    }
+
+    #[test]
+    fn test_exclude_patterns_with_file_types() {
+        let dir = TempDir::new("exclude");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join("src/main.rs"), "foo\n").unwrap();
+        fs::write(dir.join("src/notes.md"), "foo\n").unwrap();
+        fs::write(dir.join("target/out.rs"), "foo\n").unwrap();
+
+        for explicit in [false, true] {
+            let mut params = search_parameters(dir.path(), "foo");
+            params.path_patterns = vec!["!target/**".to_string()];
+            params.file_types = vec![FileTypes::builtin().get("Rust").unwrap().clone()];
+            params.flags.path_pattern_explicit = explicit;
+            let handle = SearchEngine::default().search(params);
+
+            let mut found = Vec::new();
+            for message in handle.receiver().iter() {
+                match message {
+                    SearchMessage::Result(result) => found.push(result.path),
+                    SearchMessage::Completed { .. } => break,
+                    _ => {}
+                }
+            }
+
+            assert_eq!(found, [dir.join("src/main.rs")]);
+        }
+    }
 }
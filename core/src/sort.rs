@@ -0,0 +1,80 @@
+use crate::SearchResult;
+use std::{cmp::Reverse, fs, path::Path, time::SystemTime};
+
+/// The order in which the results of a search are reported.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SortOrder {
+    /// As soon as they are found, which differs between runs.
+    #[default]
+    Unsorted,
+    Path,
+    /// Most recently modified files first.
+    Modified,
+    /// Files with the most matches first.
+    Matches,
+}
+
+impl SortOrder {
+    /// Sorts results, using the path to break ties so that the order is always the same.
+    pub fn sort(self, results: &mut [SearchResult]) {
+        let path = |result: &SearchResult| (result.path.clone(), result.inner_path.clone());
+
+        match self {
+            SortOrder::Unsorted => {}
+            SortOrder::Path => results.sort_by_cached_key(path),
+            SortOrder::Modified => {
+                results.sort_by_cached_key(|result| (Reverse(modified(&result.path)), path(result)))
+            }
+            SortOrder::Matches => {
+                results.sort_by_cached_key(|result| (Reverse(result.match_count()), path(result)))
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::SortOrder;
+    use crate::{Location, ResultEntry, SearchResult};
+    use grep::matcher::Match;
+    use std::path::{Path, PathBuf};
+
+    fn result(path: &str, matches: usize) -> SearchResult {
+        SearchResult {
+            search: 0,
+            path: PathBuf::from(path),
+            inner_path: None,
+            path_matches: Vec::new(),
+            entries: (0..matches)
+                .map(|line| ResultEntry {
+                    location: Location::Text { line: line as u64 },
+                    end_line: line as u64,
+                    content: "match".to_string(),
+                    matches: vec![Match::new(0, 5)],
+                    before_context: Vec::new(),
+                    after_context: Vec::new(),
+                    edit: None,
+                })
+                .collect(),
+            without_match: false,
+            replace_unavailable: None,
+        }
+    }
+
+    #[test]
+    fn test_sort_order() {
+        let mut results = vec![result("b", 1), result("c", 2), result("a", 1)];
+
+        SortOrder::Path.sort(&mut results);
+        let paths: Vec<&Path> = results.iter().map(|r| r.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("a"), Path::new("b"), Path::new("c")]);
+
+        SortOrder::Matches.sort(&mut results);
+        let paths: Vec<&Path> = results.iter().map(|r| r.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("c"), Path::new("a"), Path::new("b")]);
+    }
+}
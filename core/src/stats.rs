@@ -0,0 +1,231 @@
+use crate::{file_types::FileTypeMatcher, search::SearchFilters};
+use ignore::{overrides::Override, Match};
+use std::{
+    fs::{self, Metadata},
+    path::Path,
+    time::Duration,
+};
+
+/// Statistics about a completed search.
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub elapsed: Duration,
+    /// Files which passed all filters of the walker.
+    pub files_walked: u64,
+    /// Files whose content was searched.
+    pub files_searched: u64,
+    pub skipped: SkippedFiles,
+    pub bytes_read: u64,
+    pub matches: u64,
+}
+
+/// Why a file was not searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    Binary,
+    /// Excluded by a `.gitignore` or `.ignore` file.
+    Ignored,
+    Hidden,
+    /// Excluded by the file patterns or file types.
+    GlobMismatch,
+    TooLarge,
+    /// Excluded by the minimum size or modification time filters.
+    Filtered,
+    /// A document whose indexed text cannot match.
+    RuledOut,
+}
+
+/// Number of files which were not searched, by reason.
+#[derive(Debug, Clone, Default)]
+pub struct SkippedFiles {
+    pub binary: u64,
+    pub ignored: u64,
+    pub hidden: u64,
+    pub glob_mismatch: u64,
+    pub too_large: u64,
+    pub filtered: u64,
+    pub ruled_out: u64,
+}
+
+impl SkippedFiles {
+    pub fn add(&mut self, reason: SkipReason) {
+        let count = match reason {
+            SkipReason::Binary => &mut self.binary,
+            SkipReason::Ignored => &mut self.ignored,
+            SkipReason::Hidden => &mut self.hidden,
+            SkipReason::GlobMismatch => &mut self.glob_mismatch,
+            SkipReason::TooLarge => &mut self.too_large,
+            SkipReason::Filtered => &mut self.filtered,
+            SkipReason::RuledOut => &mut self.ruled_out,
+        };
+        *count += 1;
+    }
+
+    pub fn total(&self) -> u64 {
+        self.binary
+            + self.ignored
+            + self.hidden
+            + self.glob_mismatch
+            + self.too_large
+            + self.filtered
+            + self.ruled_out
+    }
+}
+
+/// Tells why the walker skips a file, applying its filters in the same order.
+///
+/// Ignore files are not taken into account, so files which are skipped
+/// without any of the other reasons applying are ignored ones.
+pub(crate) struct SkipClassifier {
+    pub overrides: Override,
+    pub types: FileTypeMatcher,
+    pub filters: SearchFilters,
+    pub skip_hidden: bool,
+    pub follow_links: bool,
+}
+
+impl SkipClassifier {
+    /// Classifies the files directly inside of a directory which the walker entered.
+    ///
+    /// Files inside of skipped directories are never reached, so they are not counted.
+    pub fn classify_dir(
+        &self,
+        base_directory: &Path,
+        directory: &Path,
+        mut f: impl FnMut(Option<SkipReason>),
+    ) {
+        let Ok(entries) = fs::read_dir(directory) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let is_file = match entry.file_type() {
+                Ok(t) if t.is_symlink() => self.follow_links && entry.path().is_file(),
+                Ok(t) => t.is_file(),
+                Err(_) => false,
+            };
+            if !is_file {
+                continue;
+            }
+
+            let path = entry.path();
+            let relative = path.strip_prefix(base_directory).unwrap_or(&path);
+            f(self.classify(relative, || fs::metadata(&path).ok()));
+        }
+    }
+
+    /// Classifies a file by its path relative to the base directory,
+    /// reading its metadata only for the size and time filters.
+    pub fn classify(
+        &self,
+        path: &Path,
+        metadata: impl FnOnce() -> Option<Metadata>,
+    ) -> Option<SkipReason> {
+        if let Some(reason) = self.classify_entry(path, false) {
+            return Some(reason);
+        }
+
+        if self.filters.max_size.is_none() && !self.filters.needs_metadata() {
+            return None;
+        }
+        let Some(metadata) = metadata() else {
+            return self
+                .filters
+                .needs_metadata()
+                .then_some(SkipReason::Filtered);
+        };
+
+        if self
+            .filters
+            .max_size
+            .is_some_and(|max| metadata.len() > max)
+        {
+            return Some(SkipReason::TooLarge);
+        }
+        if !self.filters.matches(&metadata) {
+            return Some(SkipReason::Filtered);
+        }
+
+        None
+    }
+
+    fn classify_entry(&self, path: &Path, is_dir: bool) -> Option<SkipReason> {
+        match self.overrides.matched(path, is_dir) {
+            Match::Whitelist(_) => return None,
+            Match::Ignore(_) => return Some(SkipReason::GlobMismatch),
+            Match::None => {}
+        }
+
+        if !is_dir {
+            if self.types.is_match(path) {
+                return None;
+            }
+            if !self.types.allows(path) {
+                return Some(SkipReason::GlobMismatch);
+            }
+        }
+
+        let is_hidden = path
+            .file_name()
+            .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+        (self.skip_hidden && is_hidden).then_some(SkipReason::Hidden)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SkipClassifier, SkipReason, SkippedFiles};
+    use crate::{
+        file_types::FileTypeMatcher, paths::path_overrides, testing::TempDir, SearchFilters,
+    };
+    use std::fs;
+
+    #[test]
+    fn test_skip_classifier() {
+        let dir = TempDir::new("skip");
+        fs::create_dir(dir.join("src")).unwrap();
+        fs::create_dir(dir.join("target")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/build.rs"), "fn main() {}").unwrap();
+        fs::write(dir.join("src/generated.rs"), "x".repeat(1000)).unwrap();
+        fs::write(dir.join("src/README.md"), "# Test").unwrap();
+        fs::write(dir.join("src/.hidden.rs"), "").unwrap();
+        fs::write(dir.join("target/.lock"), "").unwrap();
+
+        let patterns = ["*.rs".to_string(), "!build.rs".to_string()];
+        let classifier = SkipClassifier {
+            overrides: path_overrides(dir.path(), &patterns, false).unwrap(),
+            types: FileTypeMatcher::new(&[]).unwrap(),
+            filters: SearchFilters {
+                max_size: Some(100),
+                ..Default::default()
+            },
+            skip_hidden: true,
+            follow_links: false,
+        };
+        let classify = |directory: &str| {
+            let (mut skipped, mut not_skipped) = (SkippedFiles::default(), 0);
+            classifier.classify_dir(dir.path(), &dir.join(directory), |reason| match reason {
+                Some(reason) => skipped.add(reason),
+                None => not_skipped += 1,
+            });
+            (skipped, not_skipped)
+        };
+
+        // The hidden file is selected by the patterns.
+        let (skipped, not_skipped) = classify("src");
+        assert_eq!(not_skipped, 2);
+        assert_eq!(skipped.glob_mismatch, 2);
+        assert_eq!(skipped.too_large, 1);
+
+        let classifier = SkipClassifier {
+            overrides: path_overrides(dir.path(), &[], false).unwrap(),
+            ..classifier
+        };
+        let metadata = || fs::metadata(dir.join("target/.lock")).ok();
+        assert_eq!(
+            classifier.classify("target/.lock".as_ref(), metadata),
+            Some(SkipReason::Hidden)
+        );
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{
+    extra::ExtractorRegistry, SearchFilters, SearchFlags, SearchParameters, SearchTerm, SortOrder,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A directory for the files of a test, which is removed
+/// once the test is done, even if it failed.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// Creates an empty directory, which no other test uses at the same time.
+    pub fn new(name: &str) -> TempDir {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let path = env::temp_dir().join(format!(
+            "clapgrep-{name}-{}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        // Left behind by an earlier process with the same id.
+        _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    pub fn join(&self, path: impl AsRef<Path>) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Parameters searching the directory for the pattern with the default
+/// options, which tests override as needed.
+pub fn search_parameters(base_directory: &Path, pattern: &str) -> SearchParameters {
+    SearchParameters {
+        base_directory: base_directory.to_path_buf(),
+        terms: vec![SearchTerm::include(pattern)],
+        path_patterns: Vec::new(),
+        file_types: Vec::new(),
+        before_context: 0,
+        after_context: 0,
+        replacement: None,
+        encoding: None,
+        filters: SearchFilters::default(),
+        flags: SearchFlags::default(),
+        extractors: ExtractorRegistry::default(),
+        sort: SortOrder::default(),
+        index: None,
+        text_cache: None,
+    }
+}
@@ -1,8 +1,13 @@
 use grep::{
-    matcher::{Match, Matcher},
+    matcher::{Captures, Match, Matcher},
     searcher::{Searcher, SinkError},
 };
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 // The maximum number of bytes to execute a search to account for look-ahead.
 //
@@ -15,16 +20,97 @@ use std::io;
 // Note that this kludge is only active in multi-line mode.
 const MAX_LOOK_AHEAD: usize = 128;
 
+/// FNV-1a, which unlike the hasher of the standard library is stable,
+/// so that it can name files which outlive the process.
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// A path next to a file for writing its new content before renaming it,
+/// which no other thread or process writes to at the same time.
+pub fn temp_path(file: &Path) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+    file.with_file_name(format!(
+        "{file_name}.{}-{}.tmp",
+        process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
 pub fn find_iter_at_in_context<M, F>(
     searcher: &Searcher,
     matcher: M,
-    mut bytes: &[u8],
+    bytes: &[u8],
     range: std::ops::Range<usize>,
     mut matched: F,
 ) -> io::Result<()>
 where
     M: Matcher,
     F: FnMut(Match) -> bool,
+{
+    let bytes = context_bytes(searcher, &matcher, bytes, range.clone());
+    matcher
+        .find_iter_at(bytes, range.start, |m| {
+            if m.start() >= range.end {
+                return false;
+            }
+            matched(m)
+        })
+        .map_err(io::Error::error_message)
+}
+
+/// Replaces all matches within the range of bytes, interpolating capture
+/// group references in the replacement, and writes the result to `dst`.
+pub fn replace_in_context<M>(
+    searcher: &Searcher,
+    matcher: M,
+    bytes: &[u8],
+    range: std::ops::Range<usize>,
+    replacement: &[u8],
+    dst: &mut Vec<u8>,
+) -> io::Result<()>
+where
+    M: Matcher,
+{
+    let haystack = context_bytes(searcher, &matcher, bytes, range.clone());
+    let mut caps = matcher.new_captures().map_err(io::Error::error_message)?;
+    let mut last_match = range.start;
+
+    matcher
+        .captures_iter_at(haystack, range.start, &mut caps, |caps| {
+            let m = caps.get(0).unwrap();
+            if m.start() >= range.end {
+                return false;
+            }
+            dst.extend_from_slice(&haystack[last_match..m.start()]);
+            caps.interpolate(
+                |name| matcher.capture_index(name),
+                haystack,
+                replacement,
+                dst,
+            );
+            last_match = m.end();
+            true
+        })
+        .map_err(io::Error::error_message)?;
+
+    // The line terminator is not part of the haystack in single line mode.
+    dst.extend_from_slice(&bytes[last_match.min(range.end)..range.end]);
+    Ok(())
+}
+
+fn context_bytes<'b, M>(
+    searcher: &Searcher,
+    matcher: &M,
+    mut bytes: &'b [u8],
+    range: std::ops::Range<usize>,
+) -> &'b [u8]
+where
+    M: Matcher,
 {
     // This strange dance is to account for the possibility of look-ahead in
     // the regex. The problem here is that mat.bytes() doesn't include the
@@ -49,7 +135,7 @@ where
     // responsible for finding matches when necessary, and the printer
     // shouldn't be involved in this business in the first place. Sigh. Live
     // and learn. Abstraction boundaries are hard.
-    let is_multi_line = searcher.multi_line_with_matcher(&matcher);
+    let is_multi_line = searcher.multi_line_with_matcher(matcher);
     if is_multi_line {
         if bytes[range.end..].len() >= MAX_LOOK_AHEAD {
             bytes = &bytes[..range.end + MAX_LOOK_AHEAD];
@@ -62,14 +148,7 @@ where
         trim_line_terminator(searcher, bytes, &mut m);
         bytes = &bytes[..m.end()];
     }
-    matcher
-        .find_iter_at(bytes, range.start, |m| {
-            if m.start() >= range.end {
-                return false;
-            }
-            matched(m)
-        })
-        .map_err(io::Error::error_message)
+    bytes
 }
 
 /// Given a buf and some bounds, if there is a line terminator at the end of
@@ -1,7 +1,20 @@
-use gtk::glib;
+use crate::build::APP_ID;
+use clapgrep_core::{
+    extra::{cache::TextCache, command::CommandExtractor, ExtractorRegistry},
+    file_types::{FileType, FileTypes},
+    Index,
+};
+use gtk::{glib, subclass::prelude::*};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 mod v1;
 mod v2;
+mod v3;
+
+pub use v3::Preprocessor;
 
 thread_local! {
     static INSTANCE: Config = Config::new();
@@ -15,6 +28,89 @@ impl Config {
     fn new() -> Config {
         glib::Object::new()
     }
+
+    /// The built-in file types together with the user defined ones.
+    pub fn file_type_registry(&self) -> FileTypes {
+        let mut registry = FileTypes::builtin();
+        for (name, globs) in self.imp().inner().custom_file_types.iter() {
+            registry.add(FileType::new(name, globs));
+        }
+        registry
+    }
+
+    /// The names of the selected file types.
+    pub fn file_types(&self) -> Vec<String> {
+        self.imp().inner().file_types.clone()
+    }
+
+    pub fn set_file_types(&self, file_types: Vec<String>) {
+        self.imp().inner_mut().file_types = file_types;
+        self.imp().save();
+    }
+
+    pub fn preprocessors(&self) -> Vec<Preprocessor> {
+        self.imp().inner().preprocessors.clone()
+    }
+
+    pub fn set_preprocessors(&self, preprocessors: Vec<Preprocessor>) {
+        self.imp().inner_mut().preprocessors = preprocessors;
+        self.imp().save();
+    }
+
+    pub fn indexed_directories(&self) -> Vec<PathBuf> {
+        self.imp().inner().indexed_directories.clone()
+    }
+
+    pub fn set_indexed_directories(&self, directories: Vec<PathBuf>) {
+        self.imp().inner_mut().indexed_directories = directories;
+        self.imp().save();
+    }
+
+    /// The file storing the index of a directory.
+    pub fn index_file(&self, directory: &Path) -> PathBuf {
+        let cache_dir = glib::user_cache_dir().join(APP_ID).join("index");
+        Index::file(&cache_dir, directory)
+    }
+
+    /// The index file of an indexed directory containing the search path.
+    pub fn search_index(&self, search_path: &Path) -> Option<PathBuf> {
+        self.imp()
+            .inner()
+            .indexed_directories
+            .iter()
+            .find(|directory| search_path.starts_with(directory))
+            .map(|directory| self.index_file(directory))
+    }
+
+    /// The cache of extracted text, which searches only use if its size is not 0.
+    pub fn text_cache(&self) -> TextCache {
+        let directory = glib::user_cache_dir().join(APP_ID).join("text");
+        TextCache::new(directory, self.text_cache_size() as u64 * 1_000_000)
+    }
+
+    /// Extractors running the preprocessor commands,
+    /// or an error about the first invalid command.
+    pub fn extractor_registry(&self) -> Result<ExtractorRegistry, String> {
+        let timeout = Duration::from_secs(self.preprocessor_timeout().into());
+        let mut registry = ExtractorRegistry::default();
+
+        for preprocessor in self.imp().inner().preprocessors.iter() {
+            if preprocessor.globs.is_empty() || preprocessor.command.trim().is_empty() {
+                continue;
+            }
+
+            let argv = glib::shell_parse_argv(&preprocessor.command)
+                .map_err(|err| format!("{}: {}", preprocessor.command, err.message()))?;
+            let argv = argv.iter().map(|arg| arg.to_string_lossy().into_owned());
+            let file_type = FileType::new(&preprocessor.command, &preprocessor.globs);
+
+            registry
+                .register(CommandExtractor::new(file_type, argv.collect(), timeout))
+                .map_err(|err| format!("{}: {err}", preprocessor.globs.join(", ")))?;
+        }
+
+        Ok(registry)
+    }
 }
 
 impl Default for Config {
@@ -28,11 +124,14 @@ mod imp {
     use anyhow::Context;
     use glib::prelude::*;
     use gtk::{glib, subclass::prelude::*};
-    use std::{cell::RefCell, path::PathBuf};
+    use std::{
+        cell::{Ref, RefCell, RefMut},
+        path::PathBuf,
+    };
 
     use super::{
-        v1,
-        v2::{self, Config as InnerConfig},
+        v1, v2,
+        v3::{self, Config as InnerConfig},
     };
 
     #[derive(Default, glib::Properties)]
@@ -43,6 +142,16 @@ mod imp {
         // Search
         #[property(name = "search-path", get, set, type = PathBuf, member = search_path)]
         #[property(name = "max-search-results", get, set, type = u32, member = max_search_results)]
+        #[property(name = "context-lines", get, set, type = u32, member = context_lines)]
+        #[property(name = "encoding", get, set, type = String, member = encoding)]
+        #[property(name = "sort-order", get, set, type = String, member = sort_order)]
+        #[property(name = "live-search", get, set, type = bool, member = live_search)]
+        // Filters
+        #[property(name = "min-file-size", get, set, type = u32, member = filter_min_size)]
+        #[property(name = "max-file-size", get, set, type = u32, member = filter_max_size)]
+        #[property(name = "modified-after", get, set, type = String, member = filter_modified_after)]
+        #[property(name = "modified-before", get, set, type = String, member = filter_modified_before)]
+        #[property(name = "max-depth", get, set, type = u32, member = filter_max_depth)]
         // Window
         #[property(name = "window-width", get, set, type = i32, member = window_width)]
         #[property(name = "window-height", get, set, type = i32, member = window_height)]
@@ -50,13 +159,24 @@ mod imp {
         // Flags
         #[property(name = "path-pattern-explicit", get, set, type = bool, member = flag_path_pattern_explicit)]
         #[property(name = "case-sensitive", get, set, type = bool, member = flag_case_sensitive)]
+        #[property(name = "smart-case", get, set, type = bool, member = flag_smart_case)]
+        #[property(name = "whole-word", get, set, type = bool, member = flag_whole_word)]
         #[property(name = "include-hidden", get, set, type = bool, member = flag_include_hidden)]
         #[property(name = "include-ignored", get, set, type = bool, member = flag_include_ignored)]
         #[property(name = "disable-regex", get, set, type = bool, member = flag_disable_regex)]
+        #[property(name = "multiline", get, set, type = bool, member = flag_multiline)]
+        #[property(name = "pcre2", get, set, type = bool, member = flag_pcre2)]
+        #[property(name = "invert", get, set, type = bool, member = flag_invert)]
         // File Types
         #[property(name = "search-names", get, set, type = bool, member = search_names)]
         #[property(name = "search-pdf", get, set, type = bool, member = search_pdf)]
         #[property(name = "search-office", get, set, type = bool, member = search_office)]
+        #[property(name = "search-compressed", get, set, type = bool, member = search_compressed)]
+        #[property(name = "search-archives", get, set, type = bool, member = search_archives)]
+        // Preprocessors
+        #[property(name = "preprocessor-timeout", get, set, type = u32, member = preprocessor_timeout)]
+        // Cache
+        #[property(name = "text-cache-size", get, set, type = u32, member = text_cache_size)]
         inner: RefCell<InnerConfig>,
     }
 
@@ -78,6 +198,14 @@ mod imp {
     }
 
     impl Config {
+        pub fn inner(&self) -> Ref<'_, InnerConfig> {
+            self.inner.borrow()
+        }
+
+        pub fn inner_mut(&self) -> RefMut<'_, InnerConfig> {
+            self.inner.borrow_mut()
+        }
+
         fn config_path() -> PathBuf {
             let config_dir = glib::user_config_dir().join(APP_ID);
             std::fs::create_dir_all(&config_dir).unwrap();
@@ -109,8 +237,16 @@ mod imp {
                         std::fs::write(&config_path, config_txt).unwrap();
                     }
                     2 => {
-                        return toml::from_str::<v2::Config>(&config_txt)
-                            .context("Failed to parse v2 config file");
+                        let config_old = toml::from_str::<v2::Config>(&config_txt)
+                            .context("Failed to parse v2 config file")?;
+
+                        let config_new = v3::Config::from(config_old);
+                        let config_txt = toml::to_string(&config_new).unwrap();
+                        std::fs::write(&config_path, config_txt).unwrap();
+                    }
+                    3 => {
+                        return toml::from_str::<v3::Config>(&config_txt)
+                            .context("Failed to parse v3 config file");
                     }
                     _ => unreachable!(),
                 }
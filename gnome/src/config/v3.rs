@@ -0,0 +1,166 @@
+use crate::build::APP_VERSION;
+use gtk::glib;
+use std::{collections::BTreeMap, path::PathBuf};
+
+use super::v2;
+
+impl Config {
+    pub fn version() -> u32 {
+        3
+    }
+}
+
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub version: u32,
+    pub last_app_version: String,
+
+    pub search_path: PathBuf,
+    pub max_search_results: u32,
+    pub context_lines: u32,
+    /// Encoding of files without a byte order mark, UTF-8 if empty.
+    pub encoding: String,
+    /// How results are sorted, in the order they are found if empty.
+    pub sort_order: String,
+    /// Restart the search whenever the patterns change.
+    pub live_search: bool,
+
+    pub filter_min_size: u32,
+    pub filter_max_size: u32,
+    pub filter_modified_after: String,
+    pub filter_modified_before: String,
+    pub filter_max_depth: u32,
+
+    pub window_width: i32,
+    pub window_height: i32,
+    pub window_maximized: bool,
+
+    pub flag_path_pattern_explicit: bool,
+    pub flag_case_sensitive: bool,
+    pub flag_smart_case: bool,
+    pub flag_whole_word: bool,
+    pub flag_include_hidden: bool,
+    pub flag_include_ignored: bool,
+    pub flag_disable_regex: bool,
+    pub flag_multiline: bool,
+    pub flag_pcre2: bool,
+    pub flag_invert: bool,
+
+    pub search_names: bool,
+    pub search_pdf: bool,
+    pub search_office: bool,
+    pub search_compressed: bool,
+    pub search_archives: bool,
+
+    /// Names of the selected file types.
+    pub file_types: Vec<String>,
+    /// User defined file types, mapping names to globs.
+    pub custom_file_types: BTreeMap<String, Vec<String>>,
+
+    /// External commands which turn files into searchable text.
+    pub preprocessors: Vec<Preprocessor>,
+    /// Seconds after which a preprocessor command is stopped.
+    pub preprocessor_timeout: u32,
+
+    /// Directories whose documents are indexed.
+    pub indexed_directories: Vec<PathBuf>,
+    /// Megabytes of extracted text kept for later searches, where 0 disables the cache.
+    pub text_cache_size: u32,
+}
+
+/// An external command, which gets the path of a file as its last
+/// argument and prints the text of the file.
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Preprocessor {
+    /// Globs of the files, like `*.msg`.
+    pub globs: Vec<String>,
+    /// The command line, with arguments quoted like in a shell.
+    pub command: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: Self::version(),
+            last_app_version: APP_VERSION.to_string(),
+
+            search_path: glib::home_dir(),
+            max_search_results: 1000,
+            context_lines: 0,
+            encoding: String::new(),
+            sort_order: String::new(),
+            live_search: false,
+
+            filter_min_size: 0,
+            filter_max_size: 0,
+            filter_modified_after: String::new(),
+            filter_modified_before: String::new(),
+            filter_max_depth: 0,
+
+            window_width: 1600,
+            window_height: 900,
+            window_maximized: false,
+
+            flag_path_pattern_explicit: false,
+            flag_case_sensitive: false,
+            flag_smart_case: false,
+            flag_whole_word: false,
+            flag_include_hidden: false,
+            flag_include_ignored: false,
+            flag_disable_regex: false,
+            flag_multiline: false,
+            flag_pcre2: false,
+            flag_invert: false,
+
+            search_names: true,
+            search_pdf: true,
+            search_office: true,
+            search_compressed: true,
+            search_archives: true,
+
+            file_types: Vec::new(),
+            custom_file_types: BTreeMap::new(),
+
+            preprocessors: Vec::new(),
+            preprocessor_timeout: 30,
+
+            indexed_directories: Vec::new(),
+            text_cache_size: 500,
+        }
+    }
+}
+
+impl From<v2::Config> for Config {
+    fn from(old: v2::Config) -> Self {
+        Config {
+            version: Self::version(),
+            last_app_version: old.last_app_version,
+
+            search_path: old.search_path,
+            max_search_results: old.max_search_results,
+
+            window_width: old.window_width,
+            window_height: old.window_height,
+            window_maximized: old.window_maximized,
+
+            flag_path_pattern_explicit: old.flag_path_pattern_explicit,
+            flag_case_sensitive: old.flag_case_sensitive,
+            flag_include_hidden: old.flag_include_hidden,
+            flag_include_ignored: old.flag_include_ignored,
+            flag_disable_regex: old.flag_disable_regex,
+
+            search_names: old.search_names,
+            search_pdf: old.search_pdf,
+            search_office: old.search_office,
+            // Existing users opt in to what reads or writes more than before.
+            search_compressed: false,
+            search_archives: false,
+
+            text_cache_size: 0,
+
+            ..Self::default()
+        }
+    }
+}
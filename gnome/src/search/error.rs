@@ -0,0 +1,73 @@
+use clapgrep_core::ErrorKind;
+use gettextrs::gettext;
+use glib::prelude::*;
+use gtk::{glib, subclass::prelude::*};
+use std::cell::{Cell, RefCell};
+
+/// All kinds of errors, in the order they are grouped in.
+pub const ERROR_KINDS: [ErrorKind; 9] = [
+    ErrorKind::PermissionDenied,
+    ErrorKind::Io,
+    ErrorKind::CorruptPdf,
+    ErrorKind::PasswordProtected,
+    ErrorKind::InvalidOfficeArchive,
+    ErrorKind::SymlinkLoop,
+    ErrorKind::Preprocessor,
+    ErrorKind::Pattern,
+    ErrorKind::NestedArchive,
+];
+
+pub fn kind_label(kind: ErrorKind) -> String {
+    match kind {
+        ErrorKind::PermissionDenied => gettext("Permission Denied"),
+        ErrorKind::Io => gettext("Read Errors"),
+        ErrorKind::CorruptPdf => gettext("Corrupt PDF Files"),
+        ErrorKind::PasswordProtected => gettext("Password Protected Documents"),
+        ErrorKind::InvalidOfficeArchive => gettext("Invalid Office Files"),
+        ErrorKind::SymlinkLoop => gettext("Symbolic Link Loops"),
+        ErrorKind::Preprocessor => gettext("Failed Preprocessors"),
+        ErrorKind::Pattern => gettext("Invalid Patterns"),
+        ErrorKind::NestedArchive => gettext("Nested Archives"),
+    }
+}
+
+glib::wrapper! {
+    pub struct SearchError(ObjectSubclass<SearchErrorImp>);
+}
+
+impl SearchError {
+    pub fn new(error: clapgrep_core::SearchError) -> SearchError {
+        let search_error: SearchError = glib::Object::builder()
+            .property("path", error.path.display().to_string())
+            .property("message", error.message)
+            .property("kind_label", kind_label(error.kind))
+            .build();
+        search_error.imp().kind.set(Some(error.kind));
+        search_error
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.imp().kind.get().expect("kind is set on creation")
+    }
+}
+
+#[derive(Default, glib::Properties)]
+#[properties(wrapper_type = SearchError)]
+pub struct SearchErrorImp {
+    #[property(get, set)]
+    path: RefCell<String>,
+    #[property(get, set)]
+    message: RefCell<String>,
+    #[property(get, set)]
+    kind_label: RefCell<String>,
+    kind: Cell<Option<ErrorKind>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SearchErrorImp {
+    const NAME: &'static str = "ClapgrepSearchError";
+    type Type = SearchError;
+}
+
+#[glib::derived_properties]
+impl ObjectImpl for SearchErrorImp {}
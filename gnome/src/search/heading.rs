@@ -1,5 +1,5 @@
 use crate::search::SearchMatch;
-use clapgrep_core::Match;
+use clapgrep_core::{extra::archive, Match};
 use gtk::{
     gio,
     glib::{self, prelude::*},
@@ -21,15 +21,22 @@ impl Default for SearchHeading {
 }
 
 impl SearchHeading {
-    pub fn new(search_path: &Path, file_path: &Path, file_name_matches: &[Match]) -> SearchHeading {
+    pub fn new(
+        search_path: &Path,
+        file_path: &Path,
+        inner_path: Option<&Path>,
+        file_name_matches: &[Match],
+    ) -> SearchHeading {
         let file_path = file_path
             .strip_prefix(search_path)
             .expect("failed to strip file_path prefix");
 
         let file_name_matches_store = (!file_name_matches.is_empty()).then(|| {
-            let file_name_offset = file_path
+            // Matches are relative to the name of the archive entry, if there is one.
+            let display_path = display_path(file_path, inner_path);
+            let file_name_offset = display_path
                 .to_string_lossy()
-                .find(file_path.file_name().unwrap().to_string_lossy().as_ref())
+                .rfind(display_path.file_name().unwrap().to_string_lossy().as_ref())
                 .unwrap();
 
             let file_name_matches_store = gio::ListStore::new::<SearchMatch>();
@@ -44,6 +51,7 @@ impl SearchHeading {
         glib::Object::builder()
             .property("search_path", search_path)
             .property("file_path", file_path)
+            .property("inner_path", inner_path)
             .property("file_name_matches", file_name_matches_store)
             .build()
     }
@@ -54,6 +62,18 @@ impl SearchHeading {
 
         search_path.join(file_path)
     }
+
+    /// The relative path shown to users, including the path inside an archive.
+    pub fn display_path(&self) -> PathBuf {
+        display_path(&self.file_path(), self.inner_path().as_deref())
+    }
+}
+
+fn display_path(file_path: &Path, inner_path: Option<&Path>) -> PathBuf {
+    match inner_path {
+        Some(inner_path) => archive::entry_path(file_path, inner_path),
+        None => file_path.to_path_buf(),
+    }
 }
 
 #[derive(Default, glib::Properties)]
@@ -63,8 +83,14 @@ pub struct SearchHeadingImp {
     search_path: RefCell<PathBuf>,
     #[property(get, set)]
     file_path: RefCell<PathBuf>,
+    /// Path of the file inside the archive at `file_path`, if it is in one.
+    #[property(get, set, nullable)]
+    inner_path: RefCell<Option<PathBuf>>,
     #[property(get, set, nullable)]
     file_name_matches: RefCell<Option<gio::ListStore>>,
+    /// Why the file is excluded from replacements, if it is.
+    #[property(get, set)]
+    replace_unavailable: RefCell<String>,
 }
 
 #[glib::object_subclass]
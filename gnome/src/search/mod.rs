@@ -9,3 +9,6 @@ pub use result::SearchResult;
 
 mod heading;
 pub use heading::SearchHeading;
+
+pub mod error;
+pub use error::SearchError;
@@ -1,7 +1,8 @@
 use super::{SearchHeading, SearchResult};
+use clapgrep_core::{replace::FileEdits, SortOrder};
 use gtk::{gio, glib, prelude::*, subclass::prelude::*};
 use std::path::Path;
-use std::{cell::RefCell, path::PathBuf};
+use std::{cell::RefCell, cmp::Reverse, fs, path::PathBuf};
 
 glib::wrapper! {
     pub struct SearchModel(ObjectSubclass<SearchModelImp>)
@@ -49,7 +50,7 @@ impl SearchModel {
     pub fn extend(&self, results: impl Iterator<Item = clapgrep_core::SearchResult>) {
         let start = self.imp().data.borrow().len() as u32;
         for file_info in results {
-            if !file_info.entries.is_empty() || !file_info.path_matches.is_empty() {
+            if !file_info.is_empty() {
                 self.append_impl(file_info);
             }
         }
@@ -57,6 +58,69 @@ impl SearchModel {
 
         self.items_changed(start, 0, end - start);
     }
+
+    /// Sorts the files without searching them again, keeping the results below their heading.
+    pub fn sort(&self, order: SortOrder) {
+        if order == SortOrder::Unsorted {
+            return;
+        }
+
+        let len = {
+            let mut data = self.imp().data.borrow_mut();
+
+            let mut files: Vec<Vec<glib::Object>> = Vec::new();
+            for item in data.drain(..) {
+                match files.last_mut() {
+                    Some(file) if !item.is::<SearchHeading>() => file.push(item),
+                    _ => files.push(vec![item]),
+                }
+            }
+
+            match order {
+                SortOrder::Unsorted => {}
+                SortOrder::Path => files.sort_by_cached_key(|file| file_path(file)),
+                SortOrder::Modified => files.sort_by_cached_key(|file| {
+                    let modified = fs::metadata(heading(file).absolute_path())
+                        .and_then(|m| m.modified())
+                        .ok();
+                    (Reverse(modified), file_path(file))
+                }),
+                SortOrder::Matches => {
+                    files.sort_by_cached_key(|file| (Reverse(match_count(file)), file_path(file)))
+                }
+            }
+
+            data.extend(files.into_iter().flatten());
+            data.len() as u32
+        };
+
+        self.items_changed(0, len, len);
+    }
+
+    /// Collects the edits of all selected results, grouped by file.
+    pub fn selected_edits(&self) -> Vec<FileEdits> {
+        let mut files: Vec<FileEdits> = Vec::new();
+
+        for item in self.imp().data.borrow().iter() {
+            let Some(result) = item.downcast_ref::<SearchResult>() else {
+                continue;
+            };
+            let Some(edit) = result.edit().filter(|_| result.selected()) else {
+                continue;
+            };
+
+            let path = result.heading().absolute_path();
+            match files.last_mut() {
+                Some(file) if file.path == path => file.edits.push(edit),
+                _ => files.push(FileEdits {
+                    path,
+                    edits: vec![edit],
+                }),
+            }
+        }
+
+        files
+    }
 }
 
 #[derive(Debug, Default)]
@@ -94,22 +158,50 @@ impl ListModelImpl for SearchModelImp {
     }
 }
 
+fn heading(file: &[glib::Object]) -> SearchHeading {
+    file[0]
+        .downcast_ref::<SearchHeading>()
+        .expect("files start with their heading")
+        .clone()
+}
+
+fn file_path(file: &[glib::Object]) -> (PathBuf, Option<PathBuf>) {
+    let heading = heading(file);
+    (heading.file_path(), heading.inner_path())
+}
+
+fn match_count(file: &[glib::Object]) -> u32 {
+    let name_matches = heading(file)
+        .file_name_matches()
+        .map_or(0, |matches| matches.n_items());
+    let content_matches: u32 = file
+        .iter()
+        .filter_map(|item| item.downcast_ref::<SearchResult>())
+        .filter_map(|result| result.content_matches())
+        .map(|matches| matches.n_items())
+        .sum();
+    name_matches + content_matches
+}
+
 fn result_to_objects(
     search_path: &Path,
     result: clapgrep_core::SearchResult,
     buffer: &mut Vec<glib::Object>,
 ) {
-    let heading = SearchHeading::new(search_path, &result.path, &result.path_matches);
+    let heading = SearchHeading::new(
+        search_path,
+        &result.path,
+        result.inner_path.as_deref(),
+        &result.path_matches,
+    );
+    if let Some(reason) = result.replace_unavailable {
+        heading.set_replace_unavailable(reason);
+    }
 
     buffer.push(heading.clone().upcast::<glib::Object>());
-    let search_results = result.entries.into_iter().map(|m| {
-        let (line, page) = match m.location {
-            clapgrep_core::Location::Text { line } => (line, 0),
-            clapgrep_core::Location::Document { page, line } => (line, page),
-        };
-
-        SearchResult::new(heading.clone(), line, page, m.content, &m.matches)
-            .upcast::<glib::Object>()
-    });
+    let search_results = result
+        .entries
+        .into_iter()
+        .map(|entry| SearchResult::new(heading.clone(), entry).upcast::<glib::Object>());
     buffer.extend(search_results);
 }
@@ -1,5 +1,5 @@
 use crate::search::SearchMatch;
-use clapgrep_core::Match;
+use clapgrep_core::{Edit, Location, ResultEntry};
 use gtk::{
     gio,
     glib::{self, prelude::*},
@@ -23,33 +23,53 @@ impl Default for SearchResult {
 }
 
 impl SearchResult {
-    pub fn new(
-        heading: SearchHeading,
-        line: u64,
-        page: u64,
-        content: String,
-        content_matches: &[Match],
-    ) -> SearchResult {
-        let content = if content.contains('\0') {
-            log::debug!("Found <NULL> in '{content}'");
-            content.replace('\0', "<NULL>")
-        } else {
-            content
+    pub fn new(heading: SearchHeading, entry: ResultEntry) -> SearchResult {
+        let (line, page) = match entry.location {
+            Location::Text { line } => (line, 0),
+            Location::Document { page, line } => (line, page),
         };
 
+        let content = replace_null(entry.content);
+        let before_context = replace_null(entry.before_context.join("\n"));
+        let after_context = replace_null(entry.after_context.join("\n"));
+        let replacement = entry
+            .edit
+            .as_ref()
+            .map(|edit| {
+                let replacement = String::from_utf8_lossy(&edit.replacement);
+                replace_null(replacement.trim_ascii_end().to_string())
+            })
+            .unwrap_or_default();
+
         let content_matches_store = gio::ListStore::new::<SearchMatch>();
-        for m in content_matches {
+        for m in &entry.matches {
             let sm = SearchMatch::new(m.start() as u32, m.end() as u32);
             content_matches_store.append(&sm);
         }
 
-        glib::Object::builder()
+        let result: SearchResult = glib::Object::builder()
             .property("heading", heading)
             .property("line", line)
+            .property("end_line", entry.end_line)
             .property("page", page)
             .property("content", content)
+            .property("before_context", before_context)
+            .property("after_context", after_context)
             .property("content_matches", content_matches_store)
-            .build()
+            .property("replacement", replacement)
+            .property("selected", true)
+            .build();
+        result.imp().edit.replace(entry.edit);
+        result
+    }
+
+    /// The proposed replacement, if the search had one.
+    pub fn edit(&self) -> Option<Edit> {
+        self.imp().edit.borrow().clone()
+    }
+
+    pub fn has_edit(&self) -> bool {
+        self.imp().edit.borrow().is_some()
     }
 
     pub fn matched_strings(&self) -> HashSet<String> {
@@ -77,6 +97,15 @@ impl SearchResult {
     }
 }
 
+fn replace_null(content: String) -> String {
+    if content.contains('\0') {
+        log::debug!("Found <NULL> in '{content}'");
+        content.replace('\0', "<NULL>")
+    } else {
+        content
+    }
+}
+
 #[derive(Default, glib::Properties)]
 #[properties(wrapper_type = SearchResult)]
 pub struct SearchResultImp {
@@ -85,11 +114,23 @@ pub struct SearchResultImp {
     #[property(get, set)]
     line: Cell<u64>,
     #[property(get, set)]
+    end_line: Cell<u64>,
+    #[property(get, set)]
     page: Cell<u64>,
     #[property(get, set)]
     content: RefCell<String>,
+    #[property(get, set)]
+    before_context: RefCell<String>,
+    #[property(get, set)]
+    after_context: RefCell<String>,
     #[property(get, set, construct)]
     content_matches: RefCell<Option<gio::ListStore>>,
+    #[property(get, set)]
+    replacement: RefCell<String>,
+    /// Whether the replacement should be applied.
+    #[property(get, set)]
+    selected: Cell<bool>,
+    edit: RefCell<Option<Edit>>,
 }
 
 // Basic declaration of our type for the GObject type system
@@ -1,9 +1,16 @@
 use adw::subclass::prelude::*;
-use glib::{subclass::InitializingObject, Object};
-use gtk::{gio, glib, prelude::*, CompositeTemplate};
+use gettextrs::gettext;
+use glib::{clone, subclass::InitializingObject, Object};
+use gtk::{gio, glib, prelude::*, CompositeTemplate, StringList};
 use std::cell::RefCell;
 
-use crate::ui::SearchWindow;
+use crate::{
+    search::{
+        error::{self, ERROR_KINDS},
+        SearchError,
+    },
+    ui::SearchWindow,
+};
 
 glib::wrapper! {
     pub struct ErrorWindow(ObjectSubclass<ErrorWindowImp>)
@@ -27,8 +34,13 @@ impl ErrorWindow {
 #[template(file = "src/ui/error_window.blp")]
 #[properties(wrapper_type = ErrorWindow)]
 pub struct ErrorWindowImp {
-    #[property(get, set)]
+    #[property(get, set, construct_only)]
     pub search_window: RefCell<Option<SearchWindow>>,
+
+    #[template_child]
+    pub kind_dropdown: TemplateChild<gtk::DropDown>,
+    #[template_child]
+    pub error_list: TemplateChild<gtk::ListView>,
 }
 
 #[glib::object_subclass]
@@ -50,8 +62,65 @@ impl ObjectSubclass for ErrorWindowImp {
 #[gtk::template_callbacks]
 impl ErrorWindowImp {}
 
+impl ErrorWindowImp {
+    /// Whether an error is of the kind selected in the dropdown, where the first entry shows all.
+    fn is_shown(&self, error: &SearchError) -> bool {
+        match self.kind_dropdown.selected() as usize {
+            0 => true,
+            i => ERROR_KINDS.get(i - 1) == Some(&error.kind()),
+        }
+    }
+}
+
 #[glib::derived_properties]
-impl ObjectImpl for ErrorWindowImp {}
+impl ObjectImpl for ErrorWindowImp {
+    fn constructed(&self) {
+        self.parent_constructed();
+        let obj = self.obj();
+
+        let kinds = StringList::new(&[]);
+        kinds.append(&gettext("All Errors"));
+        for kind in ERROR_KINDS {
+            kinds.append(&error::kind_label(kind));
+        }
+        self.kind_dropdown.set_model(Some(&kinds));
+
+        let Some(search_window) = obj.search_window() else {
+            return;
+        };
+
+        let filter = gtk::CustomFilter::new(clone!(
+            #[weak]
+            obj,
+            #[upgrade_or]
+            true,
+            move |item| {
+                let error = item.downcast_ref::<SearchError>().unwrap();
+                obj.imp().is_shown(error)
+            }
+        ));
+        self.kind_dropdown.connect_selected_notify(clone!(
+            #[weak]
+            filter,
+            move |_| filter.changed(gtk::FilterChange::Different)
+        ));
+
+        // Errors are grouped by their kind, in the order of `ERROR_KINDS`.
+        let section_sorter = gtk::CustomSorter::new(|a, b| {
+            let position = |item: &glib::Object| {
+                let error = item.downcast_ref::<SearchError>().unwrap();
+                ERROR_KINDS.iter().position(|kind| *kind == error.kind())
+            };
+            position(a).cmp(&position(b)).into()
+        });
+
+        let errors = gtk::FilterListModel::new(Some(search_window.errors()), Some(filter));
+        let errors = gtk::SortListModel::new(Some(errors), None::<gtk::Sorter>);
+        errors.set_section_sorter(Some(&section_sorter));
+        self.error_list
+            .set_model(Some(&gtk::NoSelection::new(Some(errors))));
+    }
+}
 
 impl WidgetImpl for ErrorWindowImp {}
 
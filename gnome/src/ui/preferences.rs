@@ -1,12 +1,21 @@
 use crate::build::APP_PATH;
-use crate::config::Config;
+use crate::config::{Config, Preprocessor};
+use crate::i18n::gettext_f;
+use adw::prelude::{ActionRowExt, ExpanderRowExt, PreferencesGroupExt, PreferencesRowExt};
 use adw::subclass::prelude::*;
+use clapgrep_core::{split_path_patterns, Index};
+use gettextrs::gettext;
 use gtk::gio::{self, Cancellable, FileCopyFlags};
-use gtk::glib;
 use gtk::glib::subclass::InitializingObject;
-use gtk::CompositeTemplate;
+use gtk::glib::{self, clone};
+use gtk::prelude::{ButtonExt, CastNone, EditableExt, WidgetExt};
+use gtk::{CompositeTemplate, FileDialog};
 use sourceview5::prelude::{FileExt, ObjectExt};
-use std::fs;
+use std::{
+    cell::RefCell,
+    fs,
+    path::{Path, PathBuf},
+};
 
 glib::wrapper! {
     pub struct PreferencesDialog(ObjectSubclass<PreferencesDialogImp>)
@@ -32,9 +41,37 @@ pub struct PreferencesDialogImp {
     #[template_child]
     max_results_spinner: TemplateChild<adw::SpinRow>,
     #[template_child]
+    text_cache_size_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    text_cache_row: TemplateChild<adw::ActionRow>,
+    #[template_child]
     nautilus_integration_toggle: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    preprocessors_group: TemplateChild<adw::PreferencesGroup>,
+    #[template_child]
+    preprocessor_timeout_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    indexed_directories_group: TemplateChild<adw::PreferencesGroup>,
 
     config: Config,
+    preprocessor_rows: RefCell<Vec<PreprocessorRow>>,
+}
+
+/// The rows editing one preprocessor command.
+#[derive(Clone)]
+struct PreprocessorRow {
+    row: adw::ExpanderRow,
+    globs: adw::EntryRow,
+    command: adw::EntryRow,
+}
+
+impl PreprocessorRow {
+    fn preprocessor(&self) -> Preprocessor {
+        Preprocessor {
+            globs: split_path_patterns(&self.globs.text()),
+            command: self.command.text().to_string(),
+        }
+    }
 }
 
 #[glib::object_subclass]
@@ -54,9 +91,240 @@ impl ObjectSubclass for PreferencesDialogImp {
 }
 
 #[gtk::template_callbacks]
-impl PreferencesDialogImp {}
+impl PreferencesDialogImp {
+    #[template_callback]
+    fn on_add_preprocessor(&self, _: &gtk::Button) {
+        let row = self.add_preprocessor_row(&Preprocessor::default());
+        row.row.set_expanded(true);
+        row.globs.grab_focus();
+    }
+
+    #[template_callback]
+    fn on_clear_text_cache(&self, _: &gtk::Button) {
+        if let Err(err) = self.config.text_cache().clear() {
+            log::error!("Failed to clear the text cache: {err}");
+        }
+        self.update_text_cache_usage();
+    }
+
+    #[template_callback]
+    fn on_add_indexed_directory(&self, _: &gtk::Button) {
+        let obj = self.obj();
+        let window = obj.root().and_downcast::<gtk::Window>();
+
+        FileDialog::builder()
+            .title(gettext("Choose Folder to Index"))
+            .modal(true)
+            .build()
+            .select_folder(
+                window.as_ref(),
+                Cancellable::NONE,
+                clone!(
+                    #[weak]
+                    obj,
+                    move |result| {
+                        let Some(directory) = result.ok().and_then(|file| file.path()) else {
+                            return;
+                        };
+
+                        let imp = obj.imp();
+                        let mut directories = imp.config.indexed_directories();
+                        if directories.contains(&directory) {
+                            return;
+                        }
+                        directories.push(directory.clone());
+                        imp.config.set_indexed_directories(directories);
+
+                        let row = imp.add_indexed_directory_row(directory.clone());
+                        imp.update_index(&row, directory);
+                    }
+                ),
+            );
+    }
+}
 
 impl PreferencesDialogImp {
+    fn add_preprocessor_row(&self, preprocessor: &Preprocessor) -> PreprocessorRow {
+        let obj = self.obj();
+        let row = adw::ExpanderRow::builder()
+            .use_markup(false)
+            .title(&preprocessor.command)
+            .subtitle(preprocessor.globs.join(", "))
+            .build();
+        let globs = adw::EntryRow::builder()
+            .title(gettext("File Patterns, like *.msg"))
+            .text(preprocessor.globs.join(", "))
+            .build();
+        let command = adw::EntryRow::builder()
+            .title(gettext("Command"))
+            .text(&preprocessor.command)
+            .build();
+
+        let remove_button = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text(gettext("Remove Command"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        remove_button.connect_clicked(clone!(
+            #[weak]
+            obj,
+            #[weak]
+            row,
+            move |_| {
+                let imp = obj.imp();
+                imp.preprocessors_group.remove(&row);
+                imp.preprocessor_rows
+                    .borrow_mut()
+                    .retain(|preprocessor| preprocessor.row != row);
+                imp.save_preprocessors();
+            }
+        ));
+
+        for entry in [&globs, &command] {
+            entry.connect_changed(clone!(
+                #[weak]
+                obj,
+                move |_| obj.imp().save_preprocessors()
+            ));
+        }
+
+        row.add_suffix(&remove_button);
+        row.add_row(&globs);
+        row.add_row(&command);
+        self.preprocessors_group.add(&row);
+
+        let preprocessor_row = PreprocessorRow {
+            row,
+            globs,
+            command,
+        };
+        self.preprocessor_rows
+            .borrow_mut()
+            .push(preprocessor_row.clone());
+        preprocessor_row
+    }
+
+    fn add_indexed_directory_row(&self, directory: PathBuf) -> adw::ActionRow {
+        let obj = self.obj();
+        let row = adw::ActionRow::builder()
+            .title(directory.display().to_string())
+            .subtitle(self.index_size(&directory))
+            .use_markup(false)
+            .build();
+
+        let update_button = gtk::Button::builder()
+            .icon_name("view-refresh-symbolic")
+            .tooltip_text(gettext("Update Index"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        update_button.connect_clicked(clone!(
+            #[weak]
+            obj,
+            #[weak]
+            row,
+            #[strong]
+            directory,
+            move |_| obj.imp().update_index(&row, directory.clone())
+        ));
+
+        let remove_button = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text(gettext("Remove Folder"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        remove_button.connect_clicked(clone!(
+            #[weak]
+            obj,
+            #[weak]
+            row,
+            #[strong]
+            directory,
+            move |_| {
+                let imp = obj.imp();
+                let mut directories = imp.config.indexed_directories();
+                directories.retain(|indexed| indexed != &directory);
+                imp.config.set_indexed_directories(directories);
+                let _ = fs::remove_file(imp.config.index_file(&directory));
+                imp.indexed_directories_group.remove(&row);
+            }
+        ));
+
+        row.add_suffix(&update_button);
+        row.add_suffix(&remove_button);
+        self.indexed_directories_group.add(&row);
+        row
+    }
+
+    /// Indexes the documents of a directory which changed since they were
+    /// indexed on another thread, starting over if there is no usable index.
+    fn update_index(&self, row: &adw::ActionRow, directory: PathBuf) {
+        let file = self.config.index_file(&directory);
+        let extractors = self.config.extractor_registry().unwrap_or_default();
+        let task = gio::spawn_blocking(move || {
+            let mut index = Index::load(&file).unwrap_or_default();
+            index
+                .update(&directory, &extractors, &gio::Cancellable::new())
+                .map_err(|err| err.to_string())?;
+            index.save(&file).map_err(|err| err.to_string())?;
+            Ok::<_, String>(directory)
+        });
+
+        row.set_subtitle(&gettext("Indexing…"));
+        row.set_sensitive(false);
+
+        let obj = self.obj();
+        glib::spawn_future_local(clone!(
+            #[weak]
+            obj,
+            #[weak]
+            row,
+            async move {
+                let subtitle = match task.await.expect("indexing does not panic") {
+                    Ok(directory) => obj.imp().index_size(&directory),
+                    Err(err) => gettext_f("Indexing failed: {error}", &[("error", &err)]),
+                };
+                row.set_subtitle(&subtitle);
+                row.set_sensitive(true);
+            }
+        ));
+    }
+
+    fn index_size(&self, directory: &Path) -> String {
+        match fs::metadata(self.config.index_file(directory)) {
+            Ok(metadata) => gettext_f(
+                "{size} on disk",
+                &[("size", &glib::format_size(metadata.len()))],
+            ),
+            Err(_) => gettext("Not indexed yet"),
+        }
+    }
+
+    fn update_text_cache_usage(&self) {
+        let usage = self.config.text_cache().disk_usage();
+        self.text_cache_row.set_subtitle(&gettext_f(
+            "{size} on disk",
+            &[("size", &glib::format_size(usage))],
+        ));
+    }
+
+    fn save_preprocessors(&self) {
+        let preprocessors = self
+            .preprocessor_rows
+            .borrow()
+            .iter()
+            .map(|row| {
+                let preprocessor = row.preprocessor();
+                row.row.set_title(&preprocessor.command);
+                row.row.set_subtitle(&preprocessor.globs.join(", "));
+                preprocessor
+            })
+            .collect();
+        self.config.set_preprocessors(preprocessors);
+    }
+
     fn connect_nautilus_integration_toggle(&self) {
         // TODO: This should use `glib::user_data_dir()`, but that doesn't work inside of Flatpak.
         let nautilus_extension_path = glib::home_dir()
@@ -97,6 +365,29 @@ impl ObjectImpl for PreferencesDialogImp {
             .bidirectional()
             .sync_create()
             .build();
+        self.config
+            .bind_property("text-cache-size", &*self.text_cache_size_spinner, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+        self.update_text_cache_usage();
+
+        self.config
+            .bind_property(
+                "preprocessor-timeout",
+                &*self.preprocessor_timeout_spinner,
+                "value",
+            )
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        for preprocessor in self.config.preprocessors() {
+            self.add_preprocessor_row(&preprocessor);
+        }
+        for directory in self.config.indexed_directories() {
+            self.add_indexed_directory_row(directory);
+        }
 
         self.connect_nautilus_integration_toggle();
     }
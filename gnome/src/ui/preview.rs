@@ -3,6 +3,7 @@ use crate::{
     ui::preview::{pdf_preview::PdfPreview, text_preview::TextPreview},
 };
 use adw::subclass::prelude::*;
+use clapgrep_core::extra::{sniff, ExtractorRegistry};
 use gettextrs::gettext;
 use glib::subclass::InitializingObject;
 use gtk::{glib, prelude::*, CompositeTemplate};
@@ -69,30 +70,45 @@ impl ObjectSubclass for PreviewImp {
 #[gtk::template_callbacks]
 impl PreviewImp {
     fn update_preview(&self) {
-        let file = self.result.borrow().heading().absolute_path();
+        let heading = self.result.borrow().heading();
+        let file = heading.absolute_path();
 
         if !file.exists() {
             return;
         }
 
+        // Files inside of archives are previewed by their own name.
+        let inner_path = heading.inner_path();
+        let shown_file = inner_path.clone().unwrap_or(file);
+
         // Set title to file name.
-        let file_name = file.file_name().unwrap().to_string_lossy();
+        let file_name = shown_file.file_name().unwrap().to_string_lossy();
         self.title.set_title(file_name.as_ref());
 
-        if let Some(ext) = file.extension().and_then(|ext| ext.to_str()) {
-            // Try PDF
-            if clapgrep_core::extra::pdf::EXTENSIONS.contains(&ext) {
-                self.pdf_preview.set_result(self.obj().result());
-                self.views.set_visible_child(&self.some_pdf_preview.child());
-                return;
-            }
-
-            // Try Office
-            if clapgrep_core::extra::office::EXTENSIONS.contains(&ext) {
-                self.title.set_title(&gettext("Content Preview"));
-                self.views.set_visible_child(&self.no_preview.child());
-                return;
-            }
+        // Files inside of archives can only be detected by their name.
+        let registry = ExtractorRegistry::builtin();
+        let extractor = match inner_path {
+            Some(_) => registry.find(&shown_file),
+            None => registry.detect(&shown_file),
+        }
+        .cloned();
+
+        // Try PDF
+        let is_pdf = extractor
+            .as_ref()
+            .is_some_and(|e| e.mime_types().iter().any(|m| m == sniff::PDF));
+        if is_pdf && inner_path.is_none() {
+            self.pdf_preview.set_result(self.obj().result());
+            self.views.set_visible_child(&self.some_pdf_preview.child());
+            return;
+        }
+
+        // Other documents can only be previewed as their extracted text,
+        // which is not available for files inside of archives.
+        if extractor.is_some_and(|e| inner_path.is_some() || !e.supports_preview()) {
+            self.title.set_title(&gettext("Content Preview"));
+            self.views.set_visible_child(&self.no_preview.child());
+            return;
         }
 
         // Fall back to text
@@ -1,12 +1,17 @@
-use crate::search::SearchResult;
+use crate::{config::Config, search::SearchResult};
 use adw::subclass::prelude::*;
+use clapgrep_core::{
+    extra::{archive, compressed, ExtractedText, ExtractorRegistry},
+    file_types::{FileTypes, COMPRESSED},
+};
 use gtk::{
-    glib::{self, subclass::InitializingObject, Object},
+    gio,
+    glib::{self, clone, subclass::InitializingObject, Object},
     prelude::*,
     CompositeTemplate,
 };
 use sourceview5::prelude::*;
-use std::{cell::RefCell, fs, time::Duration};
+use std::{cell::RefCell, fs, path::Path, time::Duration};
 
 glib::wrapper! {
     pub struct TextPreview(ObjectSubclass<TextPreviewImp>)
@@ -26,6 +31,8 @@ impl TextPreview {
 pub struct TextPreviewImp {
     #[property(get, set)]
     pub result: RefCell<SearchResult>,
+    /// Cancels loading the content of the previous result.
+    loading: RefCell<Option<gio::Cancellable>>,
 
     #[template_child]
     pub text_view: TemplateChild<sourceview5::View>,
@@ -57,6 +64,10 @@ impl TextPreviewImp {
     }
 
     fn update_preview(&self) {
+        if let Some(loading) = self.loading.take() {
+            loading.cancel();
+        }
+
         let result = self.result.borrow();
         let file = result.heading().absolute_path();
 
@@ -64,14 +75,51 @@ impl TextPreviewImp {
             return;
         }
 
-        if let Ok(full_text) = fs::read_to_string(&file) {
+        // Extracting the text of large documents takes a while.
+        let cancellable = gio::Cancellable::new();
+        self.loading.replace(Some(cancellable.clone()));
+        let inner_path = result.heading().inner_path();
+        let page = result.page();
+        let task = gio::spawn_blocking(clone!(
+            #[strong]
+            cancellable,
+            move || load_content(&file, inner_path.as_deref(), page, &cancellable)
+        ));
+
+        let obj = self.obj();
+        glib::spawn_future_local(clone!(
+            #[weak]
+            obj,
+            async move {
+                let content = task.await.expect("loading does not panic");
+                if !cancellable.is_cancelled() {
+                    obj.imp().show_content(content);
+                }
+            }
+        ));
+    }
+
+    fn show_content(&self, content: Option<Vec<u8>>) {
+        let result = self.result.borrow();
+        let file = result.heading().absolute_path();
+        let inner_path = result.heading().inner_path();
+
+        if let Some(bytes) = content {
+            let encoding = Config::default().encoding();
+            let fallback = Some(encoding.as_str()).filter(|e| !e.is_empty());
+            let full_text = clapgrep_core::decode(&bytes, fallback);
             let buffer = self.buffer();
             let full_text_without_null = full_text.replace('\0', "<NULL>");
             buffer.set_text(&full_text_without_null);
 
             // Setup syntax highlighting
             let lm = sourceview5::LanguageManager::default();
-            let language = lm.guess_language(Some(&file), None);
+            let language = lm.guess_language(
+                Some(compressed::inner_path(
+                    inner_path.as_deref().unwrap_or(&file),
+                )),
+                None,
+            );
             buffer.set_language(language.as_ref());
             self.text_view.set_monospace(language.is_some());
 
@@ -80,6 +128,14 @@ impl TextPreviewImp {
             cursor_position.forward_lines((result.line() - 1) as i32);
             buffer.place_cursor(&cursor_position);
 
+            // Select all lines of multiline matches.
+            if result.end_line() > result.line() {
+                let mut selection_end = cursor_position.clone();
+                selection_end.forward_lines((result.end_line() - result.line()) as i32);
+                selection_end.forward_to_line_end();
+                buffer.select_range(&cursor_position, &selection_end);
+            }
+
             // Scroll to result line after 100ms.
             //
             // The delay is needed because scroll_to_iter only works
@@ -129,6 +185,49 @@ impl ObjectImpl for TextPreviewImp {
             obj.imp().update_preview();
         });
     }
+
+    fn dispose(&self) {
+        if let Some(loading) = self.loading.take() {
+            loading.cancel();
+        }
+    }
 }
 
 impl WidgetImpl for TextPreviewImp {}
+
+/// The content of a file, or of the page or archive entry a result is in.
+fn load_content(
+    file: &Path,
+    inner_path: Option<&Path>,
+    page: u64,
+    cancellable: &gio::Cancellable,
+) -> Option<Vec<u8>> {
+    let is_compressed = FileTypes::builtin()
+        .matcher(&[COMPRESSED])
+        .is_ok_and(|types| types.is_match(file));
+    let extractor = ExtractorRegistry::builtin()
+        .detect(file)
+        .filter(|extractor| extractor.supports_preview())
+        .cloned();
+
+    match (inner_path, extractor) {
+        (Some(inner_path), _) => archive::read_entry(file, inner_path).ok(),
+        (None, Some(extractor)) => extractor
+            .extract(file, cancellable)
+            .ok()
+            .map(|text| extracted_page(text, page).into_bytes()),
+        (None, None) if is_compressed => compressed::read(file).ok(),
+        (None, None) => fs::read(file).ok(),
+    }
+}
+
+/// The text of the page a result is on, where page 0 stands for text without pages.
+fn extracted_page(text: ExtractedText, page: u64) -> String {
+    match text {
+        ExtractedText::Text(text) => text,
+        ExtractedText::Pages(mut pages) => match (page as usize).checked_sub(1) {
+            Some(i) if i < pages.len() => pages.swap_remove(i),
+            _ => String::new(),
+        },
+    }
+}
@@ -26,27 +26,63 @@ pub struct ResultViewImp {
     #[property(get, set)]
     pub item: RefCell<Option<glib::Object>>,
 
+    #[template_child]
+    pub header_box: TemplateChild<gtk::Box>,
     #[template_child]
     pub header_view: TemplateChild<gtk::Label>,
     #[template_child]
+    pub replace_note: TemplateChild<gtk::Label>,
+    #[template_child]
     pub result_view: TemplateChild<gtk::Box>,
     #[template_child]
     pub result_location: TemplateChild<gtk::Label>,
     #[template_child]
     pub result_content: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub before_context_view: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub before_context_location: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub before_context_content: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub after_context_view: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub after_context_location: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub after_context_content: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub replacement_view: TemplateChild<gtk::Box>,
+    #[template_child]
+    pub replace_check: TemplateChild<gtk::CheckButton>,
+    #[template_child]
+    pub replacement_content: TemplateChild<gtk::Label>,
 
     highlight_color: RefCell<pango::Color>,
+    /// Binds the check button to the currently shown result.
+    selected_binding: RefCell<Option<glib::Binding>>,
 }
 
 impl Default for ResultViewImp {
     fn default() -> Self {
         Self {
             item: Default::default(),
+            header_box: Default::default(),
             header_view: Default::default(),
+            replace_note: Default::default(),
             result_view: Default::default(),
             result_location: Default::default(),
             result_content: Default::default(),
+            before_context_view: Default::default(),
+            before_context_location: Default::default(),
+            before_context_content: Default::default(),
+            after_context_view: Default::default(),
+            after_context_location: Default::default(),
+            after_context_content: Default::default(),
+            replacement_view: Default::default(),
+            replace_check: Default::default(),
+            replacement_content: Default::default(),
             highlight_color: RefCell::new(default_accent_color()),
+            selected_binding: Default::default(),
         }
     }
 }
@@ -71,7 +107,7 @@ impl ResultViewImp {
         let item = self.item.borrow();
 
         if let Some(item) = item.as_ref().and_then(|r| r.downcast_ref::<SearchResult>()) {
-            self.header_view.set_visible(false);
+            self.header_box.set_visible(false);
             self.result_view.set_visible(true);
             self.update_result(item);
             return;
@@ -82,7 +118,7 @@ impl ResultViewImp {
             .and_then(|r| r.downcast_ref::<SearchHeading>())
         {
             self.result_view.set_visible(false);
-            self.header_view.set_visible(true);
+            self.header_box.set_visible(true);
             self.update_heading(item);
             return;
         }
@@ -94,7 +130,12 @@ impl ResultViewImp {
         let highlight_color = self.highlight_color.borrow();
 
         self.header_view
-            .set_label(&result.file_path().to_string_lossy());
+            .set_label(&result.display_path().to_string_lossy());
+
+        let replace_unavailable = result.replace_unavailable();
+        self.replace_note
+            .set_visible(!replace_unavailable.is_empty());
+        self.replace_note.set_label(&replace_unavailable);
 
         let matches = result.file_name_matches();
         if let Some(matches) = matches {
@@ -119,12 +160,49 @@ impl ResultViewImp {
 
         self.result_content.set_text(&result.content());
 
+        let end_line = result.end_line().max(result.line());
+        let before_context = result.before_context();
+        let after_context = result.after_context();
+        let before_lines = count_lines(&before_context);
+        let after_lines = count_lines(&after_context);
+
         if result.page() == 0 {
+            // Multiline matches get one line number per line.
             self.result_location
-                .set_label(&format!("{}", result.line()));
+                .set_label(&line_numbers(result.line(), end_line));
+            self.before_context_location.set_label(&line_numbers(
+                result.line().saturating_sub(before_lines),
+                result.line().saturating_sub(1),
+            ));
+            self.after_context_location
+                .set_label(&line_numbers(end_line + 1, end_line + after_lines));
         } else {
+            // Line numbers within documents are not meaningful to the user.
             self.result_location
                 .set_label(&format!("{}", result.page()));
+            self.before_context_location.set_label("");
+            self.after_context_location.set_label("");
+        }
+
+        self.before_context_view
+            .set_visible(!before_context.is_empty());
+        self.before_context_content.set_text(&before_context);
+        self.after_context_view
+            .set_visible(!after_context.is_empty());
+        self.after_context_content.set_text(&after_context);
+
+        if let Some(binding) = self.selected_binding.take() {
+            binding.unbind();
+        }
+        self.replacement_view.set_visible(result.has_edit());
+        if result.has_edit() {
+            self.replacement_content.set_text(&result.replacement());
+            let binding = result
+                .bind_property("selected", &*self.replace_check, "active")
+                .bidirectional()
+                .sync_create()
+                .build();
+            self.selected_binding.replace(Some(binding));
         }
 
         let matches = result.content_matches();
@@ -158,6 +236,21 @@ impl ResultViewImp {
     }
 }
 
+fn count_lines(text: &str) -> u64 {
+    if text.is_empty() {
+        0
+    } else {
+        text.split('\n').count() as u64
+    }
+}
+
+fn line_numbers(first: u64, last: u64) -> String {
+    (first..=last)
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
 #[glib::derived_properties]
 impl ObjectImpl for ResultViewImp {
     fn constructed(&self) {
@@ -181,7 +274,7 @@ impl ObjectImpl for ResultViewImp {
 
     fn dispose(&self) {
         // See https://gitlab.gnome.org/GNOME/gtk/-/issues/7302
-        self.header_view.unparent();
+        self.header_box.unparent();
         self.result_view.unparent();
     }
 }
@@ -2,11 +2,19 @@ use crate::{
     build::{APP_ID, APP_VERSION},
     config::Config,
     i18n::gettext_f,
-    search::{SearchHeading, SearchModel, SearchResult},
+    search::{SearchError, SearchHeading, SearchModel, SearchResult},
     ui::{preview::Preview, ErrorWindow, ResultView},
 };
-use adw::{prelude::PreferencesGroupExt, subclass::prelude::*};
-use clapgrep_core::{SearchEngine, SearchFlags, SearchMessage, SearchParameters};
+use adw::{
+    prelude::{ActionRowExt, EntryRowExt, ExpanderRowExt, PreferencesGroupExt, PreferencesRowExt},
+    subclass::prelude::*,
+};
+use clapgrep_core::{
+    replace::{self, ReplaceBatch},
+    split_path_patterns, validate_path_patterns, validate_pattern, SearchEngine, SearchFilters,
+    SearchFlags, SearchHandle, SearchMessage, SearchParameters, SearchProgress, SearchStats,
+    SearchTerm, SortOrder, FALLBACK_ENCODINGS,
+};
 use gettextrs::gettext;
 use glib::subclass::InitializingObject;
 use glib::Object;
@@ -17,8 +25,9 @@ use gtk::{
     CompositeTemplate, FileDialog, StringList,
 };
 use std::{
-    cell::{Cell, RefCell},
+    cell::{Cell, OnceCell, RefCell},
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
 
 glib::wrapper! {
@@ -42,11 +51,21 @@ pub struct SearchWindowImp {
     pub path_pattern: RefCell<String>,
     #[property(get, set)]
     pub content_pattern: RefCell<String>,
+    /// Additional pattern rows with their exclude toggle.
+    pub extra_patterns: RefCell<Vec<(adw::EntryRow, gtk::ToggleButton)>>,
+    #[property(get, set)]
+    pub replace_mode: Cell<bool>,
+    #[property(get, set)]
+    pub replacement: RefCell<String>,
+    #[property(get, set)]
+    pub can_undo_replace: Cell<bool>,
+    /// The last applied replacements, kept for undo.
+    pub last_replacement: RefCell<Option<ReplaceBatch>>,
 
     #[property(get)]
     pub results: SearchModel,
-    #[property(get)]
-    pub errors: StringList,
+    #[property(get = Self::errors)]
+    pub errors: OnceCell<gio::ListStore>,
 
     #[property(get, set)]
     pub search_running: Cell<bool>,
@@ -54,34 +73,89 @@ pub struct SearchWindowImp {
     pub searched_files: Cell<u32>,
     #[property(get, set)]
     pub number_of_matches: Cell<u32>,
+    /// Number of files to search, once all of them have been discovered.
+    pub total_files: Cell<Option<u64>>,
+    pub scanned_bytes: Cell<u64>,
 
     #[template_child]
     pub update_banner: TemplateChild<adw::PreferencesGroup>,
     #[template_child]
     pub progress_banner: TemplateChild<adw::Banner>,
     #[template_child]
+    pub search_progress_bar: TemplateChild<gtk::ProgressBar>,
+    #[template_child]
     pub error_banner: TemplateChild<adw::Banner>,
+    #[template_child]
+    pub summary_revealer: TemplateChild<gtk::Revealer>,
+    #[template_child]
+    pub summary_label: TemplateChild<gtk::Label>,
+    #[template_child]
+    pub sort_dropdown: TemplateChild<gtk::DropDown>,
+    /// The order in which the core reports the results of the current search.
+    pub search_sort: Cell<SortOrder>,
 
+    #[template_child]
+    pub pattern_group: TemplateChild<adw::PreferencesGroup>,
+    #[template_child]
+    pub content_pattern_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
+    pub live_search_switch: TemplateChild<adw::SwitchRow>,
+    /// Starts the next search as you type, once the patterns stopped changing.
+    pub live_search_source: RefCell<Option<glib::SourceId>>,
     #[template_child]
     pub search_path_row: TemplateChild<adw::ActionRow>,
     #[template_child]
+    pub path_pattern_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
     pub path_pattern_explicit_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub file_types_row: TemplateChild<adw::ExpanderRow>,
 
     #[template_child]
     pub case_sensitive_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
+    pub smart_case_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub whole_word_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
     pub disable_regex_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
+    pub multiline_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub pcre2_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub invert_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
     pub include_hidden_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
     pub include_ignored_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
+    pub encoding_row: TemplateChild<adw::ComboRow>,
+    #[template_child]
+    pub context_lines_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    pub min_file_size_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    pub max_file_size_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
+    pub modified_after_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
+    pub modified_before_row: TemplateChild<adw::EntryRow>,
+    #[template_child]
+    pub max_depth_spinner: TemplateChild<adw::SpinRow>,
+    #[template_child]
     pub search_names_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
     pub search_pdf_switch: TemplateChild<adw::SwitchRow>,
     #[template_child]
     pub search_office_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub search_compressed_switch: TemplateChild<adw::SwitchRow>,
+    #[template_child]
+    pub search_archives_switch: TemplateChild<adw::SwitchRow>,
 
+    #[template_child]
+    pub toast_overlay: TemplateChild<adw::ToastOverlay>,
     #[template_child]
     pub results_stack: TemplateChild<gtk::Stack>,
     #[template_child]
@@ -100,6 +174,7 @@ pub struct SearchWindowImp {
     pub preview: TemplateChild<Preview>,
 
     pub engine: SearchEngine,
+    pub search: RefCell<Option<SearchHandle>>,
     pub config: Config,
 }
 
@@ -140,6 +215,21 @@ impl SearchWindowImp {
         self.start_search();
     }
 
+    #[template_callback]
+    fn on_add_pattern_clicked(&self, _: &gtk::Button) {
+        self.add_pattern_row();
+    }
+
+    #[template_callback]
+    fn on_apply_replace_clicked(&self, _: &gtk::Button) {
+        self.apply_replacements();
+    }
+
+    #[template_callback]
+    fn on_undo_replace_clicked(&self, _: &gtk::Button) {
+        self.undo_replacements();
+    }
+
     #[template_callback]
     fn on_progress_banner_activated(&self, _: &adw::Banner) {
         if self.search_running.get() {
@@ -149,6 +239,11 @@ impl SearchWindowImp {
         }
     }
 
+    #[template_callback]
+    fn on_summary_dismissed(&self, _: &gtk::Button) {
+        self.summary_revealer.set_reveal_child(false);
+    }
+
     #[template_callback]
     fn on_search_path_row_activated(&self, _: &adw::ActionRow) {
         let obj = self.obj();
@@ -174,6 +269,245 @@ impl SearchWindowImp {
             );
     }
 
+    fn add_pattern_row(&self) {
+        let obj = self.obj();
+        let row = adw::EntryRow::builder()
+            .title(gettext("Required Pattern"))
+            .build();
+
+        let exclude_button = gtk::ToggleButton::builder()
+            .label(gettext("Exclude"))
+            .tooltip_text(gettext("Skip files containing this pattern"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        exclude_button.connect_toggled(clone!(
+            #[weak]
+            row,
+            #[weak]
+            obj,
+            move |button| {
+                if button.is_active() {
+                    row.set_title(&gettext("Excluded Pattern"));
+                } else {
+                    row.set_title(&gettext("Required Pattern"));
+                }
+                obj.imp().schedule_live_search();
+            }
+        ));
+
+        let remove_button = gtk::Button::builder()
+            .icon_name("list-remove-symbolic")
+            .tooltip_text(gettext("Remove Pattern"))
+            .valign(gtk::Align::Center)
+            .css_classes(["flat"])
+            .build();
+        remove_button.connect_clicked(clone!(
+            #[weak]
+            obj,
+            #[weak]
+            row,
+            move |_| {
+                let imp = obj.imp();
+                imp.pattern_group.remove(&row);
+                imp.extra_patterns
+                    .borrow_mut()
+                    .retain(|(pattern_row, _)| pattern_row != &row);
+                imp.schedule_live_search();
+            }
+        ));
+
+        row.add_suffix(&exclude_button);
+        row.add_suffix(&remove_button);
+        row.connect_entry_activated(clone!(
+            #[weak]
+            obj,
+            move |_| {
+                obj.imp().start_search();
+            }
+        ));
+        row.connect_changed(clone!(
+            #[weak]
+            obj,
+            move |_| {
+                obj.imp().schedule_live_search();
+            }
+        ));
+
+        self.pattern_group.add(&row);
+        self.extra_patterns
+            .borrow_mut()
+            .push((row.clone(), exclude_button));
+        row.grab_focus();
+    }
+
+    fn search_terms(&self) -> Vec<SearchTerm> {
+        let mut terms = vec![SearchTerm::include(self.content_pattern.borrow().as_str())];
+
+        for (row, exclude_button) in self.extra_patterns.borrow().iter() {
+            let pattern = row.text();
+            if pattern.is_empty() {
+                continue;
+            }
+
+            terms.push(SearchTerm {
+                pattern: pattern.to_string(),
+                exclude: exclude_button.is_active(),
+            });
+        }
+
+        terms
+    }
+
+    fn populate_file_types(&self) {
+        let obj = self.obj();
+        let selected = self.config.file_types();
+
+        for file_type in self.config.file_type_registry().iter() {
+            let check = gtk::CheckButton::builder()
+                .active(selected.contains(&file_type.name))
+                .valign(gtk::Align::Center)
+                .build();
+            let row = adw::ActionRow::builder()
+                .title(&file_type.name)
+                .subtitle(file_type.globs.join(", "))
+                .use_markup(false)
+                .activatable_widget(&check)
+                .build();
+            row.add_prefix(&check);
+
+            let name = file_type.name.clone();
+            check.connect_toggled(clone!(
+                #[weak]
+                obj,
+                move |check| {
+                    let imp = obj.imp();
+                    let mut file_types = imp.config.file_types();
+                    file_types.retain(|file_type| file_type != &name);
+                    if check.is_active() {
+                        file_types.push(name.clone());
+                    }
+                    imp.config.set_file_types(file_types);
+                    imp.update_file_types_subtitle();
+                }
+            ));
+
+            self.file_types_row.add_row(&row);
+        }
+
+        self.update_file_types_subtitle();
+    }
+
+    fn update_file_types_subtitle(&self) {
+        let file_types = self.config.file_types();
+        if file_types.is_empty() {
+            self.file_types_row.set_subtitle(&gettext("All Files"));
+        } else {
+            self.file_types_row.set_subtitle(&file_types.join(", "));
+        }
+    }
+
+    /// Returns `None` if the globs are invalid, which is then shown on the row.
+    fn path_patterns(&self) -> Option<Vec<String>> {
+        let patterns = split_path_patterns(&self.path_pattern.borrow());
+
+        match validate_path_patterns(&patterns) {
+            Ok(()) => {
+                self.path_pattern_row.remove_css_class("error");
+                self.path_pattern_row.set_tooltip_text(None);
+                Some(patterns)
+            }
+            Err(err) => {
+                self.path_pattern_row.add_css_class("error");
+                self.path_pattern_row.set_tooltip_text(Some(&err));
+                None
+            }
+        }
+    }
+
+    /// Returns `false` if a pattern is invalid, which is then shown on its row.
+    fn validate_patterns(&self) -> bool {
+        let flags = self.search_flags();
+        let mut rows = vec![self.content_pattern_row.get()];
+        rows.extend(
+            self.extra_patterns
+                .borrow()
+                .iter()
+                .map(|(row, _)| row.clone()),
+        );
+
+        let mut valid = true;
+        for row in rows {
+            let pattern = row.text();
+            match validate_pattern(&pattern, &flags) {
+                Err(err) if !pattern.is_empty() => {
+                    row.add_css_class("error");
+                    row.set_tooltip_text(Some(&err));
+                    valid = false;
+                }
+                _ => {
+                    row.remove_css_class("error");
+                    row.set_tooltip_text(None);
+                }
+            }
+        }
+        valid
+    }
+
+    /// Restarts the search once the patterns stopped changing for a moment.
+    fn schedule_live_search(&self) {
+        if !self.config.live_search() {
+            return;
+        }
+
+        self.cancel_live_search();
+        let obj = self.obj();
+        let source = glib::timeout_add_local_once(
+            LIVE_SEARCH_DELAY,
+            clone!(
+                #[weak]
+                obj,
+                move || {
+                    let imp = obj.imp();
+                    imp.live_search_source.take();
+                    imp.run_search(false);
+                }
+            ),
+        );
+        self.live_search_source.replace(Some(source));
+    }
+
+    fn cancel_live_search(&self) {
+        if let Some(source) = self.live_search_source.take() {
+            source.remove();
+        }
+    }
+
+    /// Returns `None` if a date filter is invalid, which is then marked as such.
+    fn search_filters(&self) -> Option<SearchFilters> {
+        let kilobytes = |size: u32| (size > 0).then_some(size as u64 * 1000);
+        let date = |row: &adw::EntryRow| {
+            let text = row.text();
+            if text.trim().is_empty() {
+                return Some(None);
+            }
+
+            let date = parse_date(&text);
+            if date.is_none() {
+                row.add_css_class("error");
+            }
+            date.map(Some)
+        };
+
+        Some(SearchFilters {
+            min_size: kilobytes(self.config.min_file_size()),
+            max_size: kilobytes(self.config.max_file_size()),
+            modified_after: date(&self.modified_after_row)?,
+            modified_before: date(&self.modified_before_row)?,
+            max_depth: (self.config.max_depth() > 0).then_some(self.config.max_depth() as usize),
+        })
+    }
+
     fn cd_to(&self, directory: gio::File) {
         let Some(path) = directory.path() else {
             log::error!("Failed to get directory path for {:?}", directory);
@@ -252,35 +586,49 @@ impl SearchWindowImp {
         format!("{}", search_path.display())
     }
 
-    fn init_manager(&self) {
+    fn receive_messages(&self, search: &SearchHandle) {
         let app = self.obj().clone();
-        let receiver = self.engine.receiver();
+        let receiver = search.receiver();
 
         let context = glib::MainContext::default();
         context.spawn_local_with_priority(glib::Priority::LOW, async move {
             let imp = app.imp();
             while let Ok(result) = receiver.recv_async().await {
-                if imp.engine.is_current(&result) {
+                // Messages of a cancelled or replaced search are ignored.
+                if imp.is_current_search(result.search()) {
                     match result {
                         SearchMessage::Result(result) => {
-                            app.set_searched_files(app.searched_files() + 1);
                             if !result.is_empty() {
                                 let app = app.clone();
                                 glib::idle_add_local_once(move || {
+                                    // A new search may have started in the meantime.
                                     let imp = app.imp();
-                                    imp.results.append(result);
+                                    if imp.is_current_search(result.search) {
+                                        imp.results.append(result);
+                                    }
                                 });
                             }
                         }
                         SearchMessage::Error(error) => {
-                            app.errors().append(&format!(
-                                "{}: {}",
-                                error.path.display(),
-                                error.message
-                            ));
+                            app.errors().append(&SearchError::new(error));
                         }
-                        SearchMessage::Completed { .. } => {
+                        SearchMessage::Progress(progress) => {
+                            imp.show_progress(progress);
+                        }
+                        SearchMessage::Completed { search, stats } => {
+                            imp.show_summary(&stats);
                             app.set_search_running(false);
+
+                            // The order might have been changed while the search was running.
+                            if imp.sort_order() != imp.search_sort.get() {
+                                let app = app.clone();
+                                glib::idle_add_local_once(move || {
+                                    let imp = app.imp();
+                                    if imp.is_current_search(search) {
+                                        imp.results.sort(imp.sort_order());
+                                    }
+                                });
+                            }
                         }
                     }
                 }
@@ -289,48 +637,212 @@ impl SearchWindowImp {
     }
 
     fn start_search(&self) {
+        self.cancel_live_search();
+        self.run_search(true);
+    }
+
+    /// Starts a search, which only takes the focus if it was started explicitly.
+    fn run_search(&self, explicit: bool) {
+        // The previous search would keep adding to the cleared results.
+        if let Some(search) = self.search.take() {
+            search.cancel();
+            self.obj().set_search_running(false);
+        }
         self.results.clear();
 
         if self.content_pattern.borrow().is_empty() {
             return;
         }
 
+        let Some(path_patterns) = self.path_patterns() else {
+            return;
+        };
+
+        if !self.validate_patterns() {
+            return;
+        }
+
+        let Some(filters) = self.search_filters() else {
+            return;
+        };
+
+        let extractors = match self.config.extractor_registry() {
+            Ok(extractors) => extractors,
+            Err(err) => {
+                self.show_toast(&gettext_f(
+                    "Invalid preprocessor command: {error}",
+                    &[("error", &err)],
+                ));
+                return;
+            }
+        };
+
+        let registry = self.config.file_type_registry();
+        let file_types = self
+            .config
+            .file_types()
+            .iter()
+            .filter_map(|name| registry.get(name))
+            .cloned()
+            .collect();
+
         let search = SearchParameters {
             base_directory: self.config.search_path(),
-            content_pattern: self.content_pattern.borrow().to_string(),
-            path_pattern: self.path_pattern.borrow().to_string(),
-            flags: SearchFlags {
-                path_pattern_explicit: self.config.path_pattern_explicit(),
-                case_sensitive: self.config.case_sensitive(),
-                fixed_string: self.config.disable_regex(),
-                search_hidden: self.config.include_hidden(),
-                search_ignored: self.config.include_ignored(),
-                search_names: self.config.search_names(),
-                search_pdf: self.config.search_pdf(),
-                search_office: self.config.search_office(),
-
-                same_filesystem: false,
-                follow_links: true,
-            },
+            terms: self.search_terms(),
+            path_patterns,
+            file_types,
+            before_context: self.config.context_lines() as usize,
+            after_context: self.config.context_lines() as usize,
+            replacement: self
+                .replace_mode
+                .get()
+                .then(|| self.replacement.borrow().to_string()),
+            encoding: Some(self.config.encoding()).filter(|encoding| !encoding.is_empty()),
+            filters,
+            flags: self.search_flags(),
+            extractors,
+            sort: self.sort_order(),
+            index: self.config.search_index(&self.config.search_path()),
+            text_cache: Some(self.config.text_cache())
+                .filter(|_| self.config.text_cache_size() > 0),
         };
 
         log::debug!("starting search: {search:?}");
 
         self.results.clear();
         self.results.set_base_path(self.config.search_path());
-        self.errors.splice(0, self.errors.n_items(), &[]);
+        self.errors().remove_all();
+        self.total_files.set(None);
+        self.scanned_bytes.set(0);
+        self.search_progress_bar.set_fraction(0.0);
+        self.search_progress_bar.set_text(None);
+        self.summary_revealer.set_reveal_child(false);
+        self.search_sort.set(search.sort);
         self.obj().set_searched_files(0);
         self.obj().set_search_running(true);
         self.progress_banner.set_revealed(true);
-        self.split_view.set_show_content(true);
 
-        let progress_banner_button =
-            Self::find_child_by_name(self.progress_banner.upcast_ref::<gtk::Widget>(), "button")
-                .and_downcast::<gtk::Button>()
-                .expect("failed to find banner button");
-        progress_banner_button.grab_focus();
+        // Searching as you type must not take the focus from the patterns.
+        if explicit {
+            self.split_view.set_show_content(true);
+
+            let progress_banner_button = Self::find_child_by_name(
+                self.progress_banner.upcast_ref::<gtk::Widget>(),
+                "button",
+            )
+            .and_downcast::<gtk::Button>()
+            .expect("failed to find banner button");
+            progress_banner_button.grab_focus();
+        }
+
+        let search = self.engine.search(search);
+        self.receive_messages(&search);
+        self.search.replace(Some(search));
+    }
+
+    fn search_flags(&self) -> SearchFlags {
+        SearchFlags {
+            path_pattern_explicit: self.config.path_pattern_explicit(),
+            case_sensitive: self.config.case_sensitive(),
+            smart_case: self.config.smart_case(),
+            fixed_string: self.config.disable_regex(),
+            whole_word: self.config.whole_word(),
+            multiline: self.config.multiline(),
+            pcre2: self.config.pcre2(),
+            invert: self.config.invert(),
+            search_hidden: self.config.include_hidden(),
+            search_ignored: self.config.include_ignored(),
+            search_names: self.config.search_names(),
+            search_pdf: self.config.search_pdf(),
+            search_office: self.config.search_office(),
+            search_compressed: self.config.search_compressed(),
+            search_archives: self.config.search_archives(),
+
+            same_filesystem: false,
+            follow_links: true,
+        }
+    }
+
+    fn sort_order(&self) -> SortOrder {
+        let sort_order = self.config.sort_order();
+        SORT_ORDERS
+            .iter()
+            .find(|(_, name)| *name == sort_order)
+            .map_or(SortOrder::Unsorted, |(order, _)| *order)
+    }
+
+    fn errors(&self) -> gio::ListStore {
+        self.errors
+            .get_or_init(gio::ListStore::new::<SearchError>)
+            .clone()
+    }
+
+    fn is_current_search(&self, id: usize) -> bool {
+        self.search
+            .borrow()
+            .as_ref()
+            .is_some_and(|search| search.id() == id && !search.is_cancelled())
+    }
+
+    fn apply_replacements(&self) {
+        let files = self.results.selected_edits();
+        if files.is_empty() {
+            self.show_toast(&gettext("No replacements selected"));
+            return;
+        }
+
+        let backup_dir = glib::user_cache_dir().join(APP_ID).join("replace-backup");
+        match replace::apply(&files, &backup_dir) {
+            Ok(batch) => {
+                let edits = files.iter().map(|file| file.edits.len()).sum::<usize>();
+                self.show_toast(&gettext_f(
+                    "Replaced {edits} lines in {files} files",
+                    &[
+                        ("edits", &edits.to_string()),
+                        ("files", &files.len().to_string()),
+                    ],
+                ));
+                self.last_replacement.replace(Some(batch));
+                self.obj().set_can_undo_replace(true);
+                self.start_search();
+            }
+            Err(err) => {
+                log::error!("Failed to apply replacements: {err:?}");
+                self.show_toast(&gettext_f(
+                    "Nothing was replaced: {error}",
+                    &[("error", &format!("{err:#}"))],
+                ));
+            }
+        }
+    }
+
+    fn undo_replacements(&self) {
+        let Some(batch) = self.last_replacement.take() else {
+            return;
+        };
+        self.obj().set_can_undo_replace(false);
+
+        let files = batch.files().count();
+        match batch.undo() {
+            Ok(()) => {
+                self.show_toast(&gettext_f(
+                    "Restored {files} files",
+                    &[("files", &files.to_string())],
+                ));
+                self.start_search();
+            }
+            Err(err) => {
+                log::error!("Failed to undo replacements: {err:?}");
+                self.show_toast(&gettext_f(
+                    "Failed to undo the replacement: {error}",
+                    &[("error", &format!("{err:#}"))],
+                ));
+            }
+        }
+    }
 
-        self.engine.search(search);
+    fn show_toast(&self, message: &str) {
+        self.toast_overlay.add_toast(adw::Toast::new(message));
     }
 
     fn find_child_by_name(widget: &gtk::Widget, child_name: &str) -> Option<gtk::Widget> {
@@ -352,19 +864,98 @@ impl SearchWindowImp {
 
     fn stop_search(&self) {
         self.obj().set_search_running(false);
-        self.engine.cancel();
+        if let Some(search) = self.search.borrow().as_ref() {
+            search.cancel();
+        }
     }
 
-    fn update_search_progress(&self) {
-        let files = self.searched_files.get();
-        let matches = self.number_of_matches.get();
-        let message = gettext_f(
-            "Searched {files} files and found {matches} matches",
+    fn show_progress(&self, progress: SearchProgress) {
+        let total_files = progress
+            .discovery_complete
+            .then_some(progress.files_discovered);
+        self.total_files.set(total_files);
+        self.scanned_bytes.set(progress.bytes_scanned);
+
+        // The bar pulses until it is known how many files there are.
+        match total_files {
+            Some(total) if total > 0 => self.search_progress_bar.set_fraction(
+                (progress.files_searched + progress.files_ruled_out) as f64 / total as f64,
+            ),
+            _ => self.search_progress_bar.pulse(),
+        }
+
+        if let Some(file) = progress.current_file {
+            let search_path = self.config.search_path();
+            let file = file.strip_prefix(&search_path).unwrap_or(&file);
+            self.search_progress_bar
+                .set_text(Some(&file.to_string_lossy()));
+        }
+
+        self.obj()
+            .set_searched_files(progress.files_searched as u32);
+    }
+
+    fn show_summary(&self, stats: &SearchStats) {
+        let mut summary = gettext_f(
+            "Searched {searched} of {walked} files ({size}) in {seconds} seconds and found {matches} matches.",
             &[
-                ("files", &files.to_string()),
-                ("matches", &matches.to_string()),
+                ("searched", &stats.files_searched.to_string()),
+                ("walked", &stats.files_walked.to_string()),
+                ("size", &glib::format_size(stats.bytes_read)),
+                ("seconds", &format!("{:.1}", stats.elapsed.as_secs_f64())),
+                ("matches", &stats.matches.to_string()),
             ],
         );
+
+        let skipped = &stats.skipped;
+        let reasons = [
+            (skipped.binary, gettext("{count} binary")),
+            (skipped.ignored, gettext("{count} ignored")),
+            (skipped.hidden, gettext("{count} hidden")),
+            (
+                skipped.glob_mismatch,
+                gettext("{count} not matching the file pattern or types"),
+            ),
+            (skipped.too_large, gettext("{count} too large")),
+            (skipped.filtered, gettext("{count} outside of the filters")),
+            (skipped.ruled_out, gettext("{count} ruled out by the index")),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| reason.replace("{count}", &count.to_string()))
+        .collect::<Vec<_>>();
+
+        if !reasons.is_empty() {
+            summary.push('\n');
+            summary.push_str(&gettext_f(
+                "Skipped files: {reasons}",
+                &[("reasons", &reasons.join(", "))],
+            ));
+        }
+
+        self.summary_label.set_label(&summary);
+        self.summary_revealer.set_reveal_child(true);
+    }
+
+    fn update_search_progress(&self) {
+        let files = self.searched_files.get().to_string();
+        let matches = self.number_of_matches.get().to_string();
+        let size = glib::format_size(self.scanned_bytes.get());
+        let message = match self.total_files.get() {
+            Some(total) => gettext_f(
+                "Searched {files} of {total} files ({size}) and found {matches} matches",
+                &[
+                    ("files", &files),
+                    ("total", &total.to_string()),
+                    ("size", &size),
+                    ("matches", &matches),
+                ],
+            ),
+            None => gettext_f(
+                "Searched {files} files ({size}) and found {matches} matches",
+                &[("files", &files), ("size", &size), ("matches", &matches)],
+            ),
+        };
         self.progress_banner.set_title(&message);
     }
 
@@ -377,6 +968,34 @@ impl SearchWindowImp {
     }
 }
 
+/// How long the patterns have to stay unchanged before searching as you type.
+const LIVE_SEARCH_DELAY: Duration = Duration::from_millis(400);
+
+/// The orders of the sort dropdown, with the names stored in the config.
+const SORT_ORDERS: [(SortOrder, &str); 4] = [
+    (SortOrder::Unsorted, ""),
+    (SortOrder::Path, "path"),
+    (SortOrder::Modified, "modified"),
+    (SortOrder::Matches, "matches"),
+];
+
+/// Parses a `YYYY-MM-DD` date as the start of that day in local time.
+fn parse_date(text: &str) -> Option<SystemTime> {
+    let mut parts = text
+        .trim()
+        .splitn(3, '-')
+        .map(|part| part.parse::<i32>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let date = glib::DateTime::from_local(year, month, day, 0, 0, 0.0).ok()?;
+
+    let seconds = date.to_unix();
+    if seconds >= 0 {
+        SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        SystemTime::UNIX_EPOCH.checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
 #[glib::derived_properties]
 impl ObjectImpl for SearchWindowImp {
     fn constructed(&self) {
@@ -421,6 +1040,31 @@ impl ObjectImpl for SearchWindowImp {
             .sync_create()
             .build();
 
+        self.config
+            .bind_property("smart-case", &*self.smart_case_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        // Smart case decides about case sensitivity on its own.
+        self.config
+            .bind_property("smart-case", &*self.case_sensitive_switch, "sensitive")
+            .invert_boolean()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("whole-word", &*self.whole_word_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("live-search", &*self.live_search_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
         self.config
             .bind_property("include-hidden", &*self.include_hidden_switch, "active")
             .bidirectional()
@@ -433,12 +1077,130 @@ impl ObjectImpl for SearchWindowImp {
             .sync_create()
             .build();
 
+        let encodings = StringList::new(&[]);
+        encodings.append(&gettext("UTF-8"));
+        for encoding in FALLBACK_ENCODINGS {
+            encodings.append(encoding);
+        }
+        self.encoding_row.set_model(Some(&encodings));
+
+        // The first entry stands for no fallback encoding.
+        self.config
+            .bind_property("encoding", &*self.encoding_row, "selected")
+            .transform_to(|_, encoding: String| {
+                let position = FALLBACK_ENCODINGS.iter().position(|e| *e == encoding);
+                Some(position.map_or(0, |i| i as u32 + 1))
+            })
+            .transform_from(|_, selected: u32| {
+                let encoding = match selected {
+                    0 => "",
+                    i => FALLBACK_ENCODINGS
+                        .get(i as usize - 1)
+                        .copied()
+                        .unwrap_or(""),
+                };
+                Some(encoding.to_string())
+            })
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        // Positions in the sort dropdown, with the names stored in the config.
+        self.config
+            .bind_property("sort-order", &*self.sort_dropdown, "selected")
+            .transform_to(|_, sort_order: String| {
+                let position = SORT_ORDERS.iter().position(|(_, name)| *name == sort_order);
+                Some(position.unwrap_or(0) as u32)
+            })
+            .transform_from(|_, selected: u32| {
+                let name = SORT_ORDERS
+                    .get(selected as usize)
+                    .map_or("", |(_, name)| name);
+                Some(name.to_string())
+            })
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config.connect_notify_local(
+            Some("sort-order"),
+            clone!(
+                #[weak]
+                obj,
+                move |_, _| {
+                    // A running search is sorted once it has completed.
+                    if !obj.search_running() {
+                        let imp = obj.imp();
+                        imp.results.sort(imp.sort_order());
+                    }
+                }
+            ),
+        );
+
+        self.config
+            .bind_property("context-lines", &*self.context_lines_spinner, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+
         self.config
             .bind_property("disable-regex", &*self.disable_regex_switch, "active")
             .bidirectional()
             .sync_create()
             .build();
 
+        self.config
+            .bind_property("multiline", &*self.multiline_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("pcre2", &*self.pcre2_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("invert", &*self.invert_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("min-file-size", &*self.min_file_size_spinner, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("max-file-size", &*self.max_file_size_spinner, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("modified-after", &*self.modified_after_row, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("modified-before", &*self.modified_before_row, "text")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("max-depth", &*self.max_depth_spinner, "value")
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        for row in [&*self.modified_after_row, &*self.modified_before_row] {
+            row.connect_changed(|row| row.remove_css_class("error"));
+        }
+
         self.config
             .bind_property("search_names", &*self.search_names_switch, "active")
             .bidirectional()
@@ -457,6 +1219,22 @@ impl ObjectImpl for SearchWindowImp {
             .sync_create()
             .build();
 
+        self.config
+            .bind_property(
+                "search_compressed",
+                &*self.search_compressed_switch,
+                "active",
+            )
+            .bidirectional()
+            .sync_create()
+            .build();
+
+        self.config
+            .bind_property("search_archives", &*self.search_archives_switch, "active")
+            .bidirectional()
+            .sync_create()
+            .build();
+
         self.config
             .bind_property("search_path", &*self.search_path_row, "subtitle")
             .transform_to(|_, path| Some(Self::display_search_path(path)))
@@ -516,6 +1294,17 @@ impl ObjectImpl for SearchWindowImp {
             }
         });
 
+        self.populate_file_types();
+
+        obj.connect_path_pattern_notify(|obj| {
+            obj.imp().path_patterns();
+            obj.imp().schedule_live_search();
+        });
+
+        obj.connect_content_pattern_notify(|obj| {
+            obj.imp().schedule_live_search();
+        });
+
         obj.connect_searched_files_notify(|obj| {
             obj.imp().update_search_progress();
         });
@@ -523,8 +1312,6 @@ impl ObjectImpl for SearchWindowImp {
         obj.connect_number_of_matches_notify(|obj| {
             obj.imp().update_search_progress();
         });
-
-        self.init_manager();
     }
 }
 